@@ -0,0 +1,292 @@
+use std::fmt;
+use custom_error::custom_error;
+use crate::{Bracket, BracketBalanceError};
+use crate::Bracket::{Close, Open};
+
+/// Opening and closing delimiter, both may be longer than a character
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pair {
+    pub open: String,
+    pub close: String,
+}
+
+impl Pair {
+    pub fn new(open: &str, close: &str) -> Self {
+        Pair { open: open.to_string(), close: close.to_string() }
+    }
+}
+
+custom_error! {
+    #[derive(PartialEq)]
+    pub GrammarError
+    EmptyDelimiter = "delimiters must not be empty",
+    AmbiguousDelimiter{delimiter: String} = "{delimiter} already delimits a pair",
+}
+
+/// Bracket pairs checked for balance, string literals in which brackets are ignored and escape character
+#[derive(Debug, Clone, PartialEq)]
+pub struct Grammar {
+    pairs: Vec<Pair>,
+    strings: Vec<Pair>,
+    escape: Option<char>,
+}
+
+/// Line and column of a character, both counted from 1
+#[derive(Debug, Copy, Clone, PartialEq, PartialOrd)]
+pub struct Location {
+    pub line: usize,
+    pub column: usize,
+}
+
+/// Byte offsets at which lines of input start, to locate many offsets without rescanning input
+pub struct LineStarts<'a> {
+    input: &'a str,
+    starts: Vec<usize>,
+}
+
+impl<'a> LineStarts<'a> {
+    pub fn new(input: &'a str) -> Self {
+        let starts = std::iter::once(0).chain(input.match_indices('\n').map(|(newline, _)| newline + 1)).collect();
+        LineStarts { input, starts }
+    }
+
+    /// Location of character at byte `offset` of input
+    pub fn location(&self, offset: usize) -> Location {
+        let line = self.starts.partition_point(|start| *start <= offset);
+        Location { line, column: self.input[self.starts[line - 1]..offset].chars().count() + 1 }
+    }
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+/// Bracket found at byte `offset` of input
+#[derive(Debug, PartialEq)]
+pub struct Token {
+    pub offset: usize,
+    pub bracket: Bracket,
+}
+
+/// Delimiter text of a bracket in a grammar
+pub struct BracketDisplay<'a> {
+    pub(crate) bracket: &'a Bracket,
+    pub(crate) grammar: &'a Grammar,
+}
+
+impl fmt::Display for BracketDisplay<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.grammar.delimiter(self.bracket))
+    }
+}
+
+impl Grammar {
+    /// Grammar without any pairs, build it up with `with_pair` and friends
+    pub fn empty() -> Self {
+        Grammar { pairs: vec![], strings: vec![], escape: None }
+    }
+    /// Puzzle brackets `()`, `[]`, `{}` and `<>`
+    pub fn brackets() -> Self {
+        let pairs = [("(", ")"), ("[", "]"), ("{", "}"), ("<", ">")].iter()
+            .map(|(open, close)| Pair::new(open, close))
+            .collect();
+        Grammar { pairs, ..Grammar::empty() }
+    }
+
+    /// Every delimiter has to be unique among pairs, or it could not tell opening from closing
+    pub fn with_pair(mut self, open: &str, close: &str) -> Result<Self, GrammarError> {
+        if open.is_empty() || close.is_empty() {
+            return Err(GrammarError::EmptyDelimiter);
+        }
+        let taken = |delimiter: &str| self.pairs.iter().any(|pair| pair.open == delimiter || pair.close == delimiter);
+        if let Some(delimiter) = [open, close].into_iter().find(|delimiter| taken(delimiter)) {
+            return Err(GrammarError::AmbiguousDelimiter { delimiter: delimiter.to_string() });
+        }
+        if open == close {
+            return Err(GrammarError::AmbiguousDelimiter { delimiter: open.to_string() });
+        }
+        self.pairs.push(Pair::new(open, close));
+        Ok(self)
+    }
+    /// Brackets between `open` and `close` are not checked, quotes may open and close alike
+    pub fn with_string(mut self, open: &str, close: &str) -> Result<Self, GrammarError> {
+        if open.is_empty() || close.is_empty() {
+            return Err(GrammarError::EmptyDelimiter);
+        }
+        self.strings.push(Pair::new(open, close));
+        Ok(self)
+    }
+    /// Character after escape is never a delimiter
+    pub fn with_escape(mut self, escape: char) -> Self {
+        self.escape = Some(escape);
+        self
+    }
+
+    pub fn pair(&self, idx: usize) -> &Pair {
+        &self.pairs[idx]
+    }
+    /// Pair opened by `open`
+    pub fn opened_by(&self, open: &str) -> Option<usize> {
+        self.pairs.iter().position(|pair| pair.open == open)
+    }
+    pub fn delimiter(&self, bracket: &Bracket) -> &str {
+        match bracket {
+            Open(idx) => &self.pairs[*idx].open,
+            Close(idx) => &self.pairs[*idx].close,
+        }
+    }
+
+    /// Longest delimiter at the start of input
+    fn delimiter_at(&self, rest: &str) -> Option<(Bracket, usize)> {
+        self.pairs.iter().enumerate()
+            .flat_map(|(idx, pair)| [(Open(idx), &pair.open), (Close(idx), &pair.close)])
+            .filter(|(_, delimiter)| !delimiter.is_empty() && rest.starts_with(delimiter.as_str()))
+            .max_by_key(|(_, delimiter)| delimiter.len())
+            .map(|(bracket, delimiter)| (bracket, delimiter.len()))
+    }
+
+    /// Byte length of escape and escaped character at the start of input
+    fn escape_at(&self, rest: &str) -> Option<usize> {
+        let mut chars = rest.chars();
+        match (self.escape, chars.next()) {
+            (Some(escape), Some(first)) if escape == first => Some(first.len_utf8() + chars.next().map_or(0, char::len_utf8)),
+            _ => None,
+        }
+    }
+
+    /// Brackets of input in order, skipping string literals and escaped characters
+    pub fn tokens(&self, input: &str) -> Result<Vec<Token>, BracketBalanceError> {
+        match self.scan(input) {
+            (_, Some(error)) => Err(error),
+            (tokens, None) => Ok(tokens),
+        }
+    }
+
+    /// Brackets up to the end of input or of the first unterminated string literal
+    pub fn scan(&self, input: &str) -> (Vec<Token>, Option<BracketBalanceError>) {
+        let mut tokens = vec![];
+        let mut offset = 0;
+        while let Some(next) = input[offset..].chars().next() {
+            let rest = &input[offset..];
+            if let Some(len) = self.escape_at(rest) {
+                offset += len;
+            } else if let Some(string) = self.strings.iter().find(|string| rest.starts_with(string.open.as_str())) {
+                let start = offset;
+                offset += string.open.len();
+                loop {
+                    let rest = &input[offset..];
+                    if rest.is_empty() {
+                        let error = BracketBalanceError::UnterminatedString { quote: string.open.clone(), at: LineStarts::new(input).location(start) };
+                        return (tokens, Some(error));
+                    }
+                    if let Some(len) = self.escape_at(rest) {
+                        offset += len;
+                    } else if rest.starts_with(string.close.as_str()) {
+                        offset += string.close.len();
+                        break;
+                    } else {
+                        offset += rest.chars().next().unwrap().len_utf8();
+                    }
+                }
+            } else if let Some((bracket, len)) = self.delimiter_at(rest) {
+                tokens.push(Token { offset, bracket });
+                offset += len;
+            } else {
+                offset += next.len_utf8();
+            }
+        }
+        (tokens, None)
+    }
+
+    /// Closing delimiters for openers, innermost first
+    pub fn completion(&self, openers: &[usize]) -> String {
+        openers.iter().rev().map(|idx| self.pairs[*idx].close.as_str()).collect()
+    }
+}
+
+impl Default for Grammar {
+    fn default() -> Self {
+        Grammar::brackets()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{are_brackets_balanced_with, Bracket, BracketBalanceError};
+    use crate::grammar::{Grammar, GrammarError, LineStarts, Location, Token};
+
+    fn config() -> Grammar {
+        Grammar::brackets()
+            .with_pair("/*", "*/").unwrap()
+            .with_string("\"", "\"").unwrap()
+            .with_escape('\\')
+    }
+
+    #[test]
+    fn tokens() {
+        assert_eq!(Grammar::brackets().tokens("a(]").unwrap(), vec![
+            Token { offset: 1, bracket: Bracket::Open(0) },
+            Token { offset: 2, bracket: Bracket::Close(1) },
+        ]);
+        let tokens = config().tokens("/* ( */").unwrap();
+        assert_eq!(tokens.iter().map(|token| token.offset).collect::<Vec<usize>>(), vec![0, 3, 5]);
+        assert_eq!(tokens[2].bracket, Bracket::Close(4));
+        assert_eq!(tokens[2].bracket.display(&config()).to_string(), "*/");
+        assert_eq!(Bracket::Open(2).display(&config()).to_string(), "{");
+    }
+
+    #[test]
+    fn locations() {
+        let lines = LineStarts::new("ab\n\nçd\n");
+        assert_eq!(lines.location(0), Location { line: 1, column: 1 });
+        assert_eq!(lines.location(2), Location { line: 1, column: 3 });
+        assert_eq!(lines.location(3), Location { line: 2, column: 1 });
+        assert_eq!(lines.location(6), Location { line: 3, column: 2 });
+        assert_eq!(lines.location(8), Location { line: 4, column: 1 });
+    }
+
+    #[test]
+    fn config_files() {
+        let grammar = config();
+        assert_eq!(are_brackets_balanced_with(r#"{ "key": "value ) with ] brackets" }"#, &grammar), Ok(()));
+        assert_eq!(are_brackets_balanced_with(r#"{ "escaped \" quote )" }"#, &grammar), Ok(()));
+        assert_eq!(are_brackets_balanced_with(r"( \) )", &grammar), Ok(()));
+        assert_eq!(are_brackets_balanced_with("/* [ */ ]", &grammar), Err(BracketBalanceError::WrongCloseError {
+            illegal: "*/".to_string(),
+            at: Location { line: 1, column: 6 },
+            expected: "]".to_string(),
+            opened_at: Location { line: 1, column: 4 },
+        }));
+        assert_eq!(are_brackets_balanced_with("( /* ) */", &grammar), Err(BracketBalanceError::WrongCloseError {
+            illegal: ")".to_string(),
+            at: Location { line: 1, column: 6 },
+            expected: "*/".to_string(),
+            opened_at: Location { line: 1, column: 3 },
+        }));
+        assert_eq!(are_brackets_balanced_with(r#"[ "open"#, &grammar), Err(BracketBalanceError::UnterminatedString { quote: "\"".to_string(), at: Location { line: 1, column: 3 } }));
+        assert_eq!(are_brackets_balanced_with("begin begin end", &Grammar::empty().with_pair("begin", "end").unwrap()),
+                   Err(BracketBalanceError::Unbalanced { stack: vec!["begin".to_string()], opened_at: vec![Location { line: 1, column: 1 }] }));
+        // Openers stay apart even when their text runs together
+        let doubled = Grammar::brackets().with_pair("((", "))").unwrap();
+        match are_brackets_balanced_with("( ((", &doubled) {
+            Err(BracketBalanceError::Unbalanced { stack, .. }) => assert_eq!(stack, vec!["(", "(("]),
+            _ => panic!()
+        }
+        assert_eq!(doubled.opened_by("(("), Some(4));
+        assert_eq!(Grammar::brackets().completion(&[0, 2, 1]), "]})");
+    }
+
+    #[test]
+    fn invalid_delimiters() {
+        // Empty delimiters would match without consuming input
+        assert_eq!(Grammar::empty().with_string("", ""), Err(GrammarError::EmptyDelimiter));
+        assert_eq!(Grammar::empty().with_pair("(", ""), Err(GrammarError::EmptyDelimiter));
+        // A pair closed by its own opener could never be closed
+        assert_eq!(Grammar::empty().with_pair("|", "|"), Err(GrammarError::AmbiguousDelimiter { delimiter: "|".to_string() }));
+        assert_eq!(Grammar::brackets().with_pair(")", "("), Err(GrammarError::AmbiguousDelimiter { delimiter: ")".to_string() }));
+        let quoted = Grammar::brackets().with_string("|", "|").unwrap();
+        assert_eq!(are_brackets_balanced_with("( |)| )", &quoted), Ok(()));
+    }
+}
@@ -1,110 +1,127 @@
 use std::collections::HashMap;
-use std::fmt::Formatter;
-use std::{fmt, fs};
-use std::str::Chars;
+use std::fs;
 use crate::Bracket::{Close, Open};
 use custom_error::custom_error;
 use itertools::Itertools;
+use crate::grammar::{BracketDisplay, Grammar, LineStarts, Location};
+
+mod grammar;
 
 fn main() {
     println!("Part 1 : {}", part1(fs::read_to_string("day10/input_data.dat").unwrap().as_str()));
     println!("Part 2 : {}", part2(fs::read_to_string("day10/input_data.dat").unwrap().as_str()));
 }
 
+/// Delimiter of the bracket pair with given index in a grammar
 #[derive(Debug, PartialOrd, PartialEq)]
-enum Bracket {
-    Open(char),
-    Close(char),
+pub enum Bracket {
+    Open(usize),
+    Close(usize),
 }
 
 impl Bracket {
-    pub fn from_char(c: char) -> Option<Self> {
-        match c {
-            '(' | '[' | '{' | '<' => Some(Open(c)),
-            ')' => Some(Close('(')),
-            ']' => Some(Close('[')),
-            '}' => Some(Close('{')),
-            '>' => Some(Close('<')),
-            _ => None
-        }
-    }
-    pub fn close(self) -> Option<char> {
-        match self {
-            Close(_) => None,
-            Open('(') => Some(')'),
-            Open('{') => Some('}'),
-            Open('[') => Some(']'),
-            Open('<') => Some('>'),
-            _ => panic!()
-        }
-    }
-}
-
-impl fmt::Display for Bracket {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{:?}", self)
+    /// Shows delimiter text, the bracket alone only knows index of its pair
+    pub fn display<'a>(&'a self, grammar: &'a Grammar) -> BracketDisplay<'a> {
+        BracketDisplay { bracket: self, grammar }
     }
 }
 
 custom_error! {
     #[derive(PartialEq,PartialOrd)]
     pub BracketBalanceError
-    WrongCloseError{illegal: char} = "Trying to close with wrong bracket",
-    Unbalanced{stack: String} = "Unbalanced stack",
+    WrongCloseError{illegal: String, at: Location, expected: String, opened_at: Location} = "{at}: expected {expected} to close opener at {opened_at}, found {illegal}",
+    UnexpectedClose{illegal: String, at: Location} = "{at}: {illegal} does not close anything",
+    Unbalanced{stack: Vec<String>, opened_at: Vec<Location>} = @{ format!("Unbalanced stack {}", stack.join(" ")) },
+    UnterminatedString{quote: String, at: Location} = "{at}: string literal starting with {quote} is not closed",
 }
 
 pub fn are_brackets_balanced(input: &str) -> Result<(), BracketBalanceError> {
-    let mut stack: Vec<Bracket> = vec![];
-    for char in input.chars().into_iter() {
-        match Bracket::from_char(char) {
-            Some(Open(char)) => { stack.push(Bracket::Open(char)) }
-            Some(Close(open_br)) => {
-                match stack.pop() {
-                    None => {}
-                    Some(Open(c)) => {
-                        if c != open_br { return Err(BracketBalanceError::WrongCloseError { illegal: char }); }
+    are_brackets_balanced_with(input, &Grammar::default())
+}
+
+pub fn are_brackets_balanced_with(input: &str, grammar: &Grammar) -> Result<(), BracketBalanceError> {
+    match check(input, grammar, false).into_iter().next() {
+        None => Ok(()),
+        Some(error) => Err(error),
+    }
+}
+
+/// Every error of input, scanning goes on after each of them
+pub fn bracket_errors(input: &str, grammar: &Grammar) -> Vec<BracketBalanceError> {
+    check(input, grammar, true)
+}
+
+/// Errors in order of appearance, only the first one unless `all`.
+///
+/// Closer of an opener deeper on the stack closes everything above it, any other wrong closer is skipped.
+fn check(input: &str, grammar: &Grammar, all: bool) -> Vec<BracketBalanceError> {
+    let (tokens, unterminated) = grammar.scan(input);
+    let lines = LineStarts::new(input);
+    let mut errors = vec![];
+    // Pair and offset of every unclosed opener
+    let mut stack: Vec<(usize, usize)> = vec![];
+    for token in tokens {
+        if !all && !errors.is_empty() {
+            return errors;
+        }
+        match token.bracket {
+            Open(pair) => stack.push((pair, token.offset)),
+            Close(pair) => {
+                let illegal = token.bracket.display(grammar).to_string();
+                let at = lines.location(token.offset);
+                match stack.last() {
+                    None => errors.push(BracketBalanceError::UnexpectedClose { illegal, at }),
+                    Some((open, _)) if *open == pair => { stack.pop(); }
+                    Some((open, offset)) => {
+                        errors.push(BracketBalanceError::WrongCloseError {
+                            illegal,
+                            at,
+                            expected: grammar.pair(*open).close.clone(),
+                            opened_at: lines.location(*offset),
+                        });
+                        if let Some(depth) = stack.iter().rposition(|(open, _)| *open == pair) {
+                            stack.truncate(depth);
+                        }
                     }
-                    _ => panic!("Should be unreachable")
                 }
             }
-            None => {}
         }
     }
-    if stack.is_empty() {
-        Ok(())
-    } else {
-        Err(BracketBalanceError::Unbalanced {
-            stack: stack.into_iter().filter_map(|br| match br {
-                Open(c) => Some(c),
-                _ => None
-            }).collect::<String>()
-        })
+    errors.extend(unterminated);
+    if !stack.is_empty() {
+        errors.push(BracketBalanceError::Unbalanced {
+            stack: stack.iter().map(|(pair, _)| grammar.pair(*pair).open.clone()).collect(),
+            opened_at: stack.iter().map(|(_, offset)| lines.location(*offset)).collect(),
+        });
+    }
+    if !all {
+        errors.truncate(1);
     }
+    errors
 }
 
 fn part1(str: &str) -> usize {
-    let points: HashMap<char, usize> = HashMap::from_iter(vec![
-        (')', 3),
-        (']', 57),
-        ('}', 1197),
-        ('>', 25137),
+    let points: HashMap<&str, usize> = HashMap::from_iter(vec![
+        (")", 3),
+        ("]", 57),
+        ("}", 1197),
+        (">", 25137),
     ]);
     str.lines().into_iter()
         .map(are_brackets_balanced)
         .filter_map(|res| match res {
             Ok(_) => None,
-            Err(BracketBalanceError::Unbalanced { stack: _ }) => None,
-            Err(BracketBalanceError::WrongCloseError { illegal: c }) => Some(c)
+            Err(BracketBalanceError::WrongCloseError { illegal: c, .. }) => Some(c),
+            Err(_) => None,
         })
-        .map(|illegal| points.get(&illegal).unwrap())
+        .map(|illegal| points.get(illegal.as_str()).unwrap())
         .sum()
 }
 
-fn completion_chars(chars: Chars) -> Vec<char> {
-    chars.into_iter().rev()
-        .filter_map(|c| Bracket::from_char(c))
-        .filter_map(|br| br.close())
-        .collect()
+fn completion_chars(stack: &[String]) -> Vec<char> {
+    let grammar = Grammar::default();
+    let openers: Vec<usize> = stack.iter().filter_map(|open| grammar.opened_by(open)).collect();
+    grammar.completion(&openers).chars().collect()
 }
 fn part2score(brackets: &str) -> usize{
     let points: HashMap<char, usize> = HashMap::from_iter(vec![
@@ -123,10 +140,10 @@ fn part2(str: &str) -> usize {
         .map(are_brackets_balanced)
         .filter_map(|res| match res {
             Ok(_) => None,
-            Err(BracketBalanceError::Unbalanced { stack: s }) => Some(s),
-            Err(BracketBalanceError::WrongCloseError { illegal: _ }) => None
+            Err(BracketBalanceError::Unbalanced { stack: s, .. }) => Some(s),
+            Err(_) => None,
         })
-        .map(|stack|completion_chars(stack.chars()).into_iter().collect::<String>())
+        .map(|stack|completion_chars(&stack).into_iter().collect::<String>())
         .map(|missing_brackets|part2score(missing_brackets.as_str()))
         .sorted().collect::<Vec<usize>>();
     *stack.get((stack.len()-1)/2).unwrap()
@@ -134,7 +151,8 @@ fn part2(str: &str) -> usize {
 
 #[cfg(test)]
 mod test {
-    use crate::{are_brackets_balanced, BracketBalanceError, completion_chars, part1, part2, part2score};
+    use crate::{are_brackets_balanced, bracket_errors, BracketBalanceError, completion_chars, part1, part2, part2score};
+    use crate::grammar::{Grammar, Location};
 
     static TEST_DATA: &str = r#"[({(<(())[]>[[{[]{<()<>>
 [(()[<>])]({[<{<<[]>>(
@@ -153,6 +171,13 @@ mod test {
         assert_eq!(part2(TEST_DATA), 288957);
     }
 
+    fn illegal(line: &str) -> Option<String> {
+        match are_brackets_balanced(line) {
+            Err(BracketBalanceError::WrongCloseError { illegal, .. }) => Some(illegal),
+            _ => None,
+        }
+    }
+
     #[test]
     fn test_data() {
         assert_eq!(are_brackets_balanced("[]"), Ok(()));
@@ -161,11 +186,36 @@ mod test {
         assert_eq!(are_brackets_balanced("<([{}])>"), Ok(()));
         assert_eq!(are_brackets_balanced("(((((((((())))))))))"), Ok(()));
         assert_eq!(are_brackets_balanced("[<>({}){}[([])<>]]"), Ok(()));
-        assert_eq!(are_brackets_balanced("{([(<{}[<>[]}>{[]{[(<()>"), Err(BracketBalanceError::WrongCloseError { illegal: '}' }));
-        assert_eq!(are_brackets_balanced("[[<[([]))<([[{}[[()]]]"), Err(BracketBalanceError::WrongCloseError { illegal: ')' }));
-        assert_eq!(are_brackets_balanced("[{[{({}]{}}([{[{{{}}([]"), Err(BracketBalanceError::WrongCloseError { illegal: ']' }));
-        assert_eq!(are_brackets_balanced("[<(<(<(<{}))><([]([]()"), Err(BracketBalanceError::WrongCloseError { illegal: ')' }));
-        assert_eq!(are_brackets_balanced("<{([([[(<>()){}]>(<<{{"), Err(BracketBalanceError::WrongCloseError { illegal: '>' }));
+        assert_eq!(illegal("{([(<{}[<>[]}>{[]{[(<()>"), Some("}".to_string()));
+        assert_eq!(illegal("[[<[([]))<([[{}[[()]]]"), Some(")".to_string()));
+        assert_eq!(illegal("[{[{({}]{}}([{[{{{}}([]"), Some("]".to_string()));
+        assert_eq!(illegal("[<(<(<(<{}))><([]([]()"), Some(")".to_string()));
+        assert_eq!(illegal("<{([([[(<>()){}]>(<<{{"), Some(">".to_string()));
+    }
+
+    #[test]
+    fn positioned_errors() {
+        assert_eq!(are_brackets_balanced("{([(<{}[<>[]}>{[]{[(<()>"), Err(BracketBalanceError::WrongCloseError {
+            illegal: "}".to_string(),
+            at: Location { line: 1, column: 13 },
+            expected: "]".to_string(),
+            opened_at: Location { line: 1, column: 8 },
+        }));
+        assert_eq!(are_brackets_balanced("()\n ]"), Err(BracketBalanceError::UnexpectedClose { illegal: "]".to_string(), at: Location { line: 2, column: 2 } }));
+        // Closer of a deeper opener unwinds the stack
+        assert_eq!(bracket_errors("[(]", &Grammar::default()).len(), 1);
+        // Stray closers are skipped, so the first `)` of the last line closes `(` of the first one
+        let errors = bracket_errors("(]\n[(>)]\n)) {", &Grammar::default());
+        assert_eq!(errors.iter().map(|error| error.to_string()).collect::<Vec<String>>(), vec![
+            "1:2: expected ) to close opener at 1:1, found ]",
+            "2:3: expected ) to close opener at 2:2, found >",
+            "3:2: ) does not close anything",
+            "Unbalanced stack {",
+        ]);
+        match errors.last() {
+            Some(BracketBalanceError::Unbalanced { opened_at, .. }) => assert_eq!(opened_at, &vec![Location { line: 3, column: 4 }]),
+            _ => panic!()
+        }
     }
 
     #[test]
@@ -173,8 +223,8 @@ mod test {
         let line = "[({(<(())[]>[[{[]{<()<>>";
         assert_eq!(are_brackets_balanced((line.to_owned() + "}}]])})]").as_str()), Ok(()));
         match are_brackets_balanced(line) {
-            Err(BracketBalanceError::Unbalanced { stack }) =>
-                assert_eq!(completion_chars(stack.chars()), "}}]])})]".chars().collect::<Vec<char>>()),
+            Err(BracketBalanceError::Unbalanced { stack, .. }) =>
+                assert_eq!(completion_chars(&stack), "}}]])})]".chars().collect::<Vec<char>>()),
             _ => panic!()
         }
         assert_eq!(part2score("}}]])})]"),288957);
@@ -1,40 +1,42 @@
 use std::str::FromStr;
+use crate::rules::GameRules;
 
 
 #[derive(Debug, Clone)]
 pub struct BingoBoard {
     board_numbers: ndarray::Array2<u8>,
     board_markers: ndarray::Array2<bool>,
+    rules: GameRules,
 }
 
 impl BingoBoard {
+    pub fn with_rules(mut self, rules: GameRules) -> Self {
+        self.rules = rules;
+        self
+    }
+
     /// Marks a number on board and if it has won return result
     pub fn mark(&mut self, num: u8) -> Option<u64> {
-        let mut marked_indices = [0, 0];
-        self.board_numbers.indexed_iter()
-            .find(|((row, col), &entry)|
-                if num == entry {
-                    marked_indices = [*row, *col];
-                    self.board_markers[marked_indices] = true;
-                    return true;
+        let marked = self.board_numbers.indexed_iter()
+            .find(|(_, &entry)| num == entry)
+            .map(|(idx, _)| idx);
+        match marked {
+            Some(idx) => {
+                self.board_markers[idx] = true;
+                if self.rules.win_condition.is_met(&self.board_markers) {
+                    Some(self.calculate_result(num))
                 } else {
-                    return false;
-                });
-        let row_full = self.board_markers.row(marked_indices[0])
-            .iter().all(|elem| *elem);
-        let column_full = self.board_markers.column(marked_indices[1])
-            .iter().all(|elem| *elem);
-        if row_full || column_full {
-            Some(self.calculate_result(num))
-        } else {
-            None
+                    None
+                }
+            }
+            None => None
         }
     }
 
-    pub fn calculate_result(&mut self, num: u8) -> u64 {
-        self.unmarked_sum() * num as u64
+    pub fn calculate_result(&self, num: u8) -> u64 {
+        (self.rules.scoring)(self, num)
     }
-    fn unmarked_sum(&self) -> u64 {
+    pub fn unmarked_sum(&self) -> u64 {
         self.board_markers.indexed_iter()
             .filter(|((_, _), mark)| !**mark)
             .map(|((row, col), _)| self.board_numbers[[row, col]] as u64)
@@ -47,6 +49,7 @@ impl Default for BingoBoard {
         BingoBoard {
             board_numbers: ndarray::Array2::from_elem((5, 5), 0),
             board_markers: ndarray::Array2::from_elem((5, 5), false),
+            rules: GameRules::default(),
         }
     }
 }
@@ -68,6 +71,7 @@ impl FromStr for BingoBoard {
 #[cfg(test)]
 mod tests {
     use crate::bingo_board::BingoBoard;
+    use crate::rules::{GameRules, WinCondition};
 
     static BOARD_GAMES: [&str; 3] = [
         r#"22 13 17 11  0
@@ -127,4 +131,20 @@ mod tests {
         assert_eq!(board2.mark(inputs[inputs.len()-1]), None);
         assert_eq!(winning_board.mark(inputs[inputs.len()-1]), Some(4512));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn unmatched_number_does_not_win() {
+        let mut board: BingoBoard = BOARD_GAMES[0].parse().unwrap();
+        [22, 8, 21, 6].iter().for_each(|num| assert_eq!(board.mark(*num), None));
+        board.board_markers[[4, 0]] = true;
+        assert_eq!(board.mark(99), None);
+    }
+
+    #[test]
+    fn custom_rules() {
+        let rules = GameRules::new(WinCondition::FourCorners, |board, _| board.unmarked_sum());
+        let mut board: BingoBoard = BOARD_GAMES[0].parse::<BingoBoard>().unwrap().with_rules(rules);
+        [22, 0, 1].iter().for_each(|num| assert_eq!(board.mark(*num), None));
+        assert_eq!(board.mark(19), Some(300 - 22 - 1 - 19));
+    }
+}
@@ -2,20 +2,49 @@ use std::collections::VecDeque;
 use std::fs;
 use std::str::FromStr;
 use crate::bingo_board::BingoBoard;
+use crate::rules::{GameRules, unmarked_sum_times_draw, WinCondition};
+use crate::simulation::{BoardOutcome, SimulationConfig};
 
 mod bingo_board;
+mod rules;
+mod simulation;
 
+/// Win condition can be given as first argument, see `WinCondition::from_str`
 fn main() {
+    let rules = match std::env::args().nth(1) {
+        Some(arg) => GameRules::new(arg.parse().expect("Unknown win condition"), unmarked_sum_times_draw),
+        None => GameRules::new(WinCondition::RowOrColumn, unmarked_sum_times_draw),
+    };
     {
         let input = fs::read_to_string("day4/input_data.dat");
-        let mut game: BingoGame = input.unwrap().as_str().parse().unwrap();
+        let mut game: BingoGame = input.unwrap().as_str().parse::<BingoGame>().unwrap().with_rules(rules);
         println!("Winning score: {}", game.winning_score());
     }
     {
         let input = fs::read_to_string("day4/input_data.dat");
-        let mut game: BingoGame = input.unwrap().as_str().parse().unwrap();
+        let mut game: BingoGame = input.unwrap().as_str().parse::<BingoGame>().unwrap().with_rules(rules);
         println!("Losing score: {}", game.losing_score());
     }
+    {
+        let input = fs::read_to_string("day4/input_data.dat");
+        let game: BingoGame = input.unwrap().as_str().parse::<BingoGame>().unwrap().with_rules(rules);
+        let outcomes = game.simulate(&SimulationConfig { runs: 200, seed: 2021 });
+        let (board, outcome) = outcomes.iter().enumerate()
+            .max_by_key(|(_, outcome)| outcome.first_wins)
+            .unwrap();
+        let probability = outcome.first_win_probability();
+        println!("Most likely winner: board {} ({:.3} [{:.3}, {:.3}])",
+                 board, probability.value, probability.lower, probability.upper);
+        if let Some(draw) = outcome.mean_win_draw() {
+            println!("Mean winning draw: {:.1} [{:.1}, {:.1}]", draw.value, draw.lower, draw.upper);
+        }
+        let (board, outcome) = outcomes.iter().enumerate()
+            .max_by_key(|(_, outcome)| outcome.last_wins)
+            .unwrap();
+        let probability = outcome.last_win_probability();
+        println!("Most likely loser: board {} ({:.3} [{:.3}, {:.3}])",
+                 board, probability.value, probability.lower, probability.upper);
+    }
 }
 
 #[derive(Debug)]
@@ -25,6 +54,17 @@ struct BingoGame {
 }
 
 impl BingoGame {
+    pub fn with_rules(mut self, rules: GameRules) -> Self {
+        self.boards = self.boards.into_iter()
+            .map(|board| board.with_rules(rules))
+            .collect();
+        self
+    }
+    /// Monte Carlo estimate of winning chances of every board with numbers drawn in random order
+    pub fn simulate(&self, config: &SimulationConfig) -> Vec<BoardOutcome> {
+        let pool: Vec<u8> = self.numbers.iter().copied().collect();
+        simulation::simulate(&self.boards, &pool, config)
+    }
     pub fn winning_score(&mut self) -> u64 {
         for num in &self.numbers {
             for bingo_board in self.boards.as_mut_slice() {
@@ -77,6 +117,7 @@ impl FromStr for BingoGame {
 mod tests {
     use std::fs;
     use crate::BingoGame;
+    use crate::rules::{GameRules, WinCondition};
 
     #[test]
     fn winning_from_test_data() {
@@ -93,6 +134,12 @@ mod tests {
         println!("{:?}", game);
         assert_eq!(game.losing_score(), 1924);
     }
-}
-
 
+    #[test]
+    fn blackout_from_test_data() {
+        let input = fs::read_to_string("test_data.dat");
+        let mut game: BingoGame = input.unwrap().as_str().parse::<BingoGame>().unwrap()
+            .with_rules(GameRules::new(WinCondition::Blackout, |_, num| num as u64));
+        assert_eq!(game.winning_score(), 3);
+    }
+}
@@ -0,0 +1,141 @@
+use std::str::FromStr;
+use ndarray::Array2;
+use crate::bingo_board::BingoBoard;
+
+/// Condition that has to be met by board markers for a board to win
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum WinCondition {
+    /// Any complete row or column
+    RowOrColumn,
+    /// Any complete row, column or one of two diagonals
+    RowColumnOrDiagonal,
+    /// All four corners marked
+    FourCorners,
+    /// Every number on the card marked
+    Blackout,
+    /// At least N marks in any row, column or diagonal
+    MarksInLine(usize),
+}
+
+impl WinCondition {
+    pub fn is_met(&self, markers: &Array2<bool>) -> bool {
+        match self {
+            WinCondition::RowOrColumn => rows_and_columns(markers).into_iter()
+                .any(|(marked, len)| marked == len),
+            WinCondition::RowColumnOrDiagonal => rows_and_columns(markers).into_iter()
+                .chain(diagonals(markers))
+                .any(|(marked, len)| marked == len),
+            WinCondition::FourCorners => {
+                let (rows, cols) = markers.dim();
+                rows > 0 && cols > 0 && [[0, 0], [0, cols - 1], [rows - 1, 0], [rows - 1, cols - 1]]
+                    .into_iter()
+                    .all(|idx| markers[idx])
+            }
+            WinCondition::Blackout => markers.iter().all(|mark| *mark),
+            WinCondition::MarksInLine(count) => rows_and_columns(markers).into_iter()
+                .chain(diagonals(markers))
+                .any(|(marked, _)| marked >= *count),
+        }
+    }
+}
+
+/// Parses `row-or-column`, `diagonal`, `corners`, `blackout` or `marks-in-line:N`
+impl FromStr for WinCondition {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once(':') {
+            Some(("marks-in-line", count)) => count.parse().map(WinCondition::MarksInLine).map_err(|_| ()),
+            Some(_) => Err(()),
+            None => match s {
+                "row-or-column" => Ok(WinCondition::RowOrColumn),
+                "diagonal" => Ok(WinCondition::RowColumnOrDiagonal),
+                "corners" => Ok(WinCondition::FourCorners),
+                "blackout" => Ok(WinCondition::Blackout),
+                _ => Err(()),
+            },
+        }
+    }
+}
+
+/// (marked, length) of every row and column
+fn rows_and_columns(markers: &Array2<bool>) -> Vec<(usize, usize)> {
+    markers.rows().into_iter()
+        .chain(markers.columns())
+        .map(|line| (line.iter().filter(|mark| **mark).count(), line.len()))
+        .collect()
+}
+
+/// (marked, length) of both diagonals, only square boards have them
+fn diagonals(markers: &Array2<bool>) -> Vec<(usize, usize)> {
+    let (rows, cols) = markers.dim();
+    if rows != cols || rows == 0 {
+        return vec![];
+    }
+    let main = (0..rows).filter(|&i| markers[[i, i]]).count();
+    let anti = (0..rows).filter(|&i| markers[[i, cols - 1 - i]]).count();
+    vec![(main, rows), (anti, rows)]
+}
+
+/// Calculates score of a winning board given the number that was drawn last
+pub type Scoring = fn(&BingoBoard, u8) -> u64;
+
+/// Puzzle scoring: sum of all unmarked numbers multiplied by the winning number
+pub fn unmarked_sum_times_draw(board: &BingoBoard, num: u8) -> u64 {
+    board.unmarked_sum() * num as u64
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct GameRules {
+    pub win_condition: WinCondition,
+    pub scoring: Scoring,
+}
+
+impl GameRules {
+    pub fn new(win_condition: WinCondition, scoring: Scoring) -> Self {
+        GameRules { win_condition, scoring }
+    }
+}
+
+impl Default for GameRules {
+    fn default() -> Self {
+        GameRules::new(WinCondition::RowOrColumn, unmarked_sum_times_draw)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::rules::WinCondition;
+
+    #[test]
+    fn win_conditions() {
+        let diagonal = ndarray::arr2(&[
+            [true, false, false],
+            [false, true, false],
+            [true, false, true],
+        ]);
+        assert!(!WinCondition::RowOrColumn.is_met(&diagonal));
+        assert!(WinCondition::RowColumnOrDiagonal.is_met(&diagonal));
+        assert!(!WinCondition::FourCorners.is_met(&diagonal));
+        assert!(!WinCondition::Blackout.is_met(&diagonal));
+        assert!(WinCondition::MarksInLine(2).is_met(&diagonal));
+        assert!(!WinCondition::MarksInLine(4).is_met(&diagonal));
+
+        let corners = ndarray::arr2(&[
+            [true, false, true],
+            [false, false, false],
+            [true, false, true],
+        ]);
+        assert!(WinCondition::FourCorners.is_met(&corners));
+        assert!(!WinCondition::RowColumnOrDiagonal.is_met(&corners));
+        assert!(WinCondition::Blackout.is_met(&ndarray::Array2::from_elem((3, 3), true)));
+    }
+
+    #[test]
+    fn parse_win_conditions() {
+        assert_eq!("corners".parse(), Ok(WinCondition::FourCorners));
+        assert_eq!("marks-in-line:3".parse(), Ok(WinCondition::MarksInLine(3)));
+        assert_eq!("marks-in-line:x".parse::<WinCondition>(), Err(()));
+        assert_eq!("diagonals".parse::<WinCondition>(), Err(()));
+    }
+}
@@ -0,0 +1,178 @@
+use rand::SeedableRng;
+use rand::seq::SliceRandom;
+use rand_chacha::ChaCha8Rng;
+use rayon::prelude::*;
+use crate::bingo_board::BingoBoard;
+
+/// z-score of the 95% confidence level
+const Z_95: f64 = 1.96;
+/// Runs simulated with one RNG stream, keeps results independent of thread count
+const RUNS_PER_CHUNK: usize = 64;
+
+#[derive(Debug, Copy, Clone)]
+pub struct SimulationConfig {
+    pub runs: usize,
+    pub seed: u64,
+}
+
+/// Probability estimate with its 95% Wilson score interval
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Estimate {
+    pub value: f64,
+    pub lower: f64,
+    pub upper: f64,
+}
+
+impl Estimate {
+    fn wilson(successes: usize, trials: usize) -> Self {
+        if trials == 0 {
+            return Estimate { value: 0.0, lower: 0.0, upper: 1.0 };
+        }
+        let n = trials as f64;
+        let p = successes as f64 / n;
+        let z2 = Z_95 * Z_95;
+        let denominator = 1.0 + z2 / n;
+        let centre = (p + z2 / (2.0 * n)) / denominator;
+        let half_width = Z_95 * (p * (1.0 - p) / n + z2 / (4.0 * n * n)).sqrt() / denominator;
+        Estimate { value: p, lower: (centre - half_width).max(0.0), upper: (centre + half_width).min(1.0) }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BoardOutcome {
+    pub runs: usize,
+    pub first_wins: usize,
+    pub last_wins: usize,
+    pub never_won: usize,
+    /// Number of runs in which board won at given draw index
+    pub win_draws: Vec<usize>,
+}
+
+impl BoardOutcome {
+    fn new(draws: usize) -> Self {
+        BoardOutcome { runs: 0, first_wins: 0, last_wins: 0, never_won: 0, win_draws: vec![0; draws] }
+    }
+    fn merge(mut self, other: &BoardOutcome) -> Self {
+        self.runs += other.runs;
+        self.first_wins += other.first_wins;
+        self.last_wins += other.last_wins;
+        self.never_won += other.never_won;
+        self.win_draws.iter_mut()
+            .zip(&other.win_draws)
+            .for_each(|(count, other_count)| *count += other_count);
+        self
+    }
+    pub fn first_win_probability(&self) -> Estimate {
+        Estimate::wilson(self.first_wins, self.runs)
+    }
+    pub fn last_win_probability(&self) -> Estimate {
+        Estimate::wilson(self.last_wins, self.runs)
+    }
+    /// Mean draw index at which board wins with 95% confidence interval, None if it never won
+    pub fn mean_win_draw(&self) -> Option<Estimate> {
+        let won = self.runs - self.never_won;
+        if won == 0 {
+            return None;
+        }
+        let mean = self.win_draws.iter().enumerate()
+            .map(|(draw, count)| (draw * count) as f64)
+            .sum::<f64>() / won as f64;
+        let variance = self.win_draws.iter().enumerate()
+            .map(|(draw, count)| (draw as f64 - mean).powi(2) * *count as f64)
+            .sum::<f64>() / won as f64;
+        let half_width = Z_95 * (variance / won as f64).sqrt();
+        Some(Estimate { value: mean, lower: mean - half_width, upper: mean + half_width })
+    }
+}
+
+/// Draw index at which board wins when numbers are drawn in given order
+fn winning_draw(board: &BingoBoard, draws: &[u8]) -> Option<usize> {
+    let mut board = board.clone();
+    draws.iter().position(|num| board.mark(*num).is_some())
+}
+
+fn simulate_chunk(boards: &[BingoBoard], pool: &[u8], seed: u64, chunk: usize, runs: usize) -> Vec<BoardOutcome> {
+    let mut rng = ChaCha8Rng::seed_from_u64(seed);
+    rng.set_stream(chunk as u64);
+    let mut draws = pool.to_vec();
+    let mut outcomes = vec![BoardOutcome::new(pool.len()); boards.len()];
+    for _ in 0..runs {
+        draws.shuffle(&mut rng);
+        let wins: Vec<(usize, usize)> = boards.iter()
+            .map(|board| winning_draw(board, &draws))
+            .enumerate()
+            .filter_map(|(board_idx, draw)| draw.map(|draw| (draw, board_idx)))
+            .collect();
+        outcomes.iter_mut().for_each(|outcome| {
+            outcome.runs += 1;
+            outcome.never_won += 1;
+        });
+        wins.iter().for_each(|&(draw, board_idx)| {
+            outcomes[board_idx].never_won -= 1;
+            outcomes[board_idx].win_draws[draw] += 1;
+        });
+        // Ties are resolved in board order like in a regular game
+        if let Some(&(_, first)) = wins.iter().min() {
+            outcomes[first].first_wins += 1;
+        }
+        if let Some(&(_, last)) = wins.iter().max() {
+            outcomes[last].last_wins += 1;
+        }
+    }
+    outcomes
+}
+
+/// Estimates for every board how likely it is to win first and last when pool is drawn in random order
+pub fn simulate(boards: &[BingoBoard], pool: &[u8], config: &SimulationConfig) -> Vec<BoardOutcome> {
+    let chunks = config.runs.div_ceil(RUNS_PER_CHUNK);
+    (0..chunks).into_par_iter()
+        .map(|chunk| {
+            let runs = RUNS_PER_CHUNK.min(config.runs - chunk * RUNS_PER_CHUNK);
+            simulate_chunk(boards, pool, config.seed, chunk, runs)
+        })
+        .collect::<Vec<Vec<BoardOutcome>>>()
+        .iter()
+        .fold(vec![BoardOutcome::new(pool.len()); boards.len()], |acc, chunk| {
+            acc.into_iter()
+                .zip(chunk)
+                .map(|(total, outcome)| total.merge(outcome))
+                .collect()
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use crate::BingoGame;
+    use crate::simulation::{Estimate, SimulationConfig};
+
+    #[test]
+    fn wilson_interval() {
+        let estimate = Estimate::wilson(50, 100);
+        assert_eq!(estimate.value, 0.5);
+        assert!((estimate.lower - 0.404).abs() < 0.001);
+        assert!((estimate.upper - 0.596).abs() < 0.001);
+        assert_eq!(Estimate::wilson(0, 0), Estimate { value: 0.0, lower: 0.0, upper: 1.0 });
+    }
+
+    #[test]
+    fn reproducible_with_test_data() {
+        let input = fs::read_to_string("test_data.dat");
+        let game: BingoGame = input.unwrap().as_str().parse().unwrap();
+        let config = SimulationConfig { runs: 500, seed: 2021 };
+        let outcomes = game.simulate(&config);
+        let single_thread = rayon::ThreadPoolBuilder::new().num_threads(1).build().unwrap()
+            .install(|| game.simulate(&config));
+        assert_eq!(outcomes, single_thread);
+        assert_eq!(outcomes.iter().map(|outcome| outcome.first_wins).sum::<usize>(), 500);
+        assert_eq!(outcomes.iter().map(|outcome| outcome.last_wins).sum::<usize>(), 500);
+        outcomes.iter().for_each(|outcome| {
+            assert_eq!(outcome.never_won, 0);
+            assert_eq!(outcome.win_draws.iter().sum::<usize>(), 500);
+            let first = outcome.first_win_probability();
+            assert!(first.lower <= first.value && first.value <= first.upper);
+            let mean = outcome.mean_win_draw().unwrap();
+            assert!(mean.value >= 4.0 && mean.value < 27.0);
+        });
+    }
+}
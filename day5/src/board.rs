@@ -1,56 +1,121 @@
-use crate::line::Line;
+use std::collections::HashMap;
+use crate::line::{Line, Rasterization};
+use crate::point::Point;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct BoundingBox {
+    pub min: Point,
+    pub max: Point,
+}
+
+impl BoundingBox {
+    fn extend(self, point: Point) -> Self {
+        BoundingBox {
+            min: Point { x: self.min.x.min(point.x), y: self.min.y.min(point.y) },
+            max: Point { x: self.max.x.max(point.x), y: self.max.y.max(point.y) },
+        }
+    }
+}
+
+/// Sparse board, only points covered by at least one line are stored
+#[derive(Debug, Clone, Default)]
 pub struct Board {
-    fields: ndarray::Array2<u16>,
+    fields: HashMap<Point, u32>,
+    /// Count of points marked exactly `idx` times
+    histogram: Vec<usize>,
+    bounding_box: Option<BoundingBox>,
 }
 
 impl Board {
-    pub fn marked_at_least(&self, p0: u16) -> usize {
-        self.fields.iter()
-            .filter(|&&marks| marks >= p0)
-            .count()
+    pub fn marked_at_least(&self, p0: u32) -> usize {
+        self.histogram.iter()
+            .skip(p0.max(1) as usize)
+            .sum()
     }
     pub fn mark(&mut self, line: Line) {
-        assert!(line.is_horizontal_vertical_or_diagonal(), "Only horizontal, vertical or diagonal lines are supported for now");
-        line.point_vec().into_iter().for_each(|point| self.fields[[point.x as usize, point.y as usize]] += 1);
+        line.point_vec().into_iter().for_each(|point| self.mark_point(point));
+    }
+    pub fn mark_with(&mut self, line: Line, rasterization: Rasterization) {
+        line.rasterize(rasterization).into_iter().for_each(|point| self.mark_point(point));
     }
-    pub fn with_dim(dim: usize) -> Board {
-        Board {
-            fields: ndarray::Array2::from_elem((dim, dim), 0),
+    pub fn mark_point(&mut self, point: Point) {
+        let marks = self.fields.entry(point).or_insert(0);
+        *marks += 1;
+        let marks = *marks as usize;
+        if self.histogram.len() <= marks {
+            self.histogram.resize(marks + 1, 0);
         }
+        self.histogram[marks] += 1;
+        if marks > 1 {
+            self.histogram[marks - 1] -= 1;
+        }
+        self.bounding_box = Some(match self.bounding_box {
+            Some(bounding_box) => bounding_box.extend(point),
+            None => BoundingBox { min: point, max: point },
+        });
+    }
+    /// Highest number of lines covering a single point
+    pub fn max_marks(&self) -> u32 {
+        self.histogram.len().saturating_sub(1) as u32
+    }
+    pub fn marks_at(&self, point: Point) -> u32 {
+        self.fields.get(&point).copied().unwrap_or(0)
+    }
+    /// Smallest box containing all marked points, None for empty board
+    pub fn bounding_box(&self) -> Option<BoundingBox> {
+        self.bounding_box
     }
 }
 
 #[cfg(test)]
 mod board_tests {
-    use crate::board::Board;
+    use crate::board::{Board, BoundingBox};
+    use crate::point::Point;
 
     #[test]
     fn mark_test() {
-        let mut board = Board::with_dim(10);
+        let mut board = Board::default();
         board.mark("1,1 -> 1,3".parse().unwrap());
         board.mark("9,7 -> 7,7".parse().unwrap());
         println!("{:?}", board);
-        assert_eq!(board.fields[[1, 1]], 1);
-        assert_eq!(board.fields[[1, 2]], 1);
-        assert_eq!(board.fields[[1, 3]], 1);
-        assert_eq!(board.fields[[9, 7]], 1);
-        assert_eq!(board.fields[[8, 7]], 1);
-        assert_eq!(board.fields[[7, 7]], 1);
+        assert_eq!(board.marks_at(Point { x: 1, y: 1 }), 1);
+        assert_eq!(board.marks_at(Point { x: 1, y: 2 }), 1);
+        assert_eq!(board.marks_at(Point { x: 1, y: 3 }), 1);
+        assert_eq!(board.marks_at(Point { x: 9, y: 7 }), 1);
+        assert_eq!(board.marks_at(Point { x: 8, y: 7 }), 1);
+        assert_eq!(board.marks_at(Point { x: 7, y: 7 }), 1);
     }
 
     #[test]
     fn part2_mark_test() {
-        let mut board = Board::with_dim(10);
+        let mut board = Board::default();
         board.mark("1,1 -> 3,3".parse().unwrap());
         board.mark("9,7 -> 7,9".parse().unwrap());
         println!("{:?}", board);
-        assert_eq!(board.fields[[1, 1]], 1);
-        assert_eq!(board.fields[[2, 2]], 1);
-        assert_eq!(board.fields[[3, 3]], 1);
-        assert_eq!(board.fields[[9, 7]], 1);
-        assert_eq!(board.fields[[8, 8]], 1);
-        assert_eq!(board.fields[[7, 9]], 1);
+        assert_eq!(board.marks_at(Point { x: 1, y: 1 }), 1);
+        assert_eq!(board.marks_at(Point { x: 2, y: 2 }), 1);
+        assert_eq!(board.marks_at(Point { x: 3, y: 3 }), 1);
+        assert_eq!(board.marks_at(Point { x: 9, y: 7 }), 1);
+        assert_eq!(board.marks_at(Point { x: 8, y: 8 }), 1);
+        assert_eq!(board.marks_at(Point { x: 7, y: 9 }), 1);
+    }
+
+    #[test]
+    fn unbounded_mark_test() {
+        let mut board = Board::default();
+        assert_eq!(board.bounding_box(), None);
+        board.mark("-5,2000 -> -5,2002".parse().unwrap());
+        board.mark("-6,2001 -> 100000,2001".parse().unwrap());
+        board.mark("-5,2001 -> -3,2003".parse().unwrap());
+        assert_eq!(board.marks_at(Point { x: -5, y: 2001 }), 3);
+        assert_eq!(board.marks_at(Point { x: -4, y: 2000 }), 0);
+        assert_eq!(board.marked_at_least(0), board.marked_at_least(1));
+        assert_eq!(board.marked_at_least(2), 1);
+        assert_eq!(board.marked_at_least(3), 1);
+        assert_eq!(board.marked_at_least(4), 0);
+        assert_eq!(board.bounding_box(), Some(BoundingBox {
+            min: Point { x: -6, y: 2000 },
+            max: Point { x: 100000, y: 2003 },
+        }));
     }
 }
@@ -0,0 +1,176 @@
+use std::io;
+use std::io::{ErrorKind, Write};
+use crate::board::{Board, BoundingBox};
+use crate::point::Point;
+
+/// Colour scale stops from a single line up to the highest overlap count
+const SCALE: [[u8; 3]; 4] = [[32, 32, 128], [0, 160, 160], [240, 200, 0], [255, 48, 32]];
+const EMPTY: [u8; 3] = [0, 0, 0];
+/// Longest side of an exported image, lines far away from the rest would otherwise need huge images
+pub const MAX_SIDE: usize = 8192;
+
+/// Renders overlap counts of a board, covers the bounding box of marked points
+pub struct Heatmap<'a> {
+    board: &'a Board,
+    highlight: Option<u32>,
+}
+
+impl<'a> Heatmap<'a> {
+    pub fn new(board: &'a Board) -> Self {
+        Heatmap { board, highlight: None }
+    }
+    /// Dims all cells covered by less than `marks` lines
+    pub fn highlight_at_least(mut self, marks: u32) -> Self {
+        self.highlight = Some(marks);
+        self
+    }
+
+    pub fn colour(&self, marks: u32) -> [u8; 3] {
+        if marks == 0 {
+            return EMPTY;
+        }
+        let max = self.board.max_marks().max(2);
+        let position = (marks.min(max) - 1) as f64 / (max - 1) as f64 * (SCALE.len() - 1) as f64;
+        let lower = (position.floor() as usize).min(SCALE.len() - 2);
+        let fraction = position - lower as f64;
+        let mut colour = [0; 3];
+        colour.iter_mut().enumerate().for_each(|(channel, value)| {
+            let (from, to) = (SCALE[lower][channel] as f64, SCALE[lower + 1][channel] as f64);
+            *value = (from + (to - from) * fraction).round() as u8;
+        });
+        match self.highlight {
+            Some(threshold) if marks < threshold => colour.map(|value| value / 3),
+            _ => colour,
+        }
+    }
+
+    fn area(&self) -> BoundingBox {
+        self.board.bounding_box()
+            .unwrap_or(BoundingBox { min: Point { x: 0, y: 0 }, max: Point { x: 0, y: 0 } })
+    }
+    pub fn width(&self) -> usize {
+        let area = self.area();
+        (area.max.x as i64 - area.min.x as i64) as usize + 1
+    }
+    pub fn height(&self) -> usize {
+        let area = self.area();
+        (area.max.y as i64 - area.min.y as i64) as usize + 1
+    }
+    fn check_size(&self) -> io::Result<()> {
+        if self.width() > MAX_SIDE || self.height() > MAX_SIDE {
+            return Err(io::Error::new(ErrorKind::InvalidInput,
+                                      format!("Heatmap of {}x{} exceeds {} pixels per side", self.width(), self.height(), MAX_SIDE)));
+        }
+        Ok(())
+    }
+
+    /// Row by row counts, x grows to the right and y downwards
+    fn rows(&self) -> Vec<Vec<u32>> {
+        let area = self.area();
+        (area.min.y..=area.max.y)
+            .map(|y| (area.min.x..=area.max.x)
+                .map(|x| self.board.marks_at(Point { x, y }))
+                .collect())
+            .collect()
+    }
+    fn rgb_data(&self) -> Vec<u8> {
+        self.rows().into_iter()
+            .flatten()
+            .flat_map(|marks| self.colour(marks))
+            .collect()
+    }
+
+    pub fn write_ppm<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        self.check_size()?;
+        write!(writer, "P6\n{} {}\n255\n", self.width(), self.height())?;
+        writer.write_all(&self.rgb_data())
+    }
+
+    pub fn write_png<W: Write>(&self, writer: W) -> Result<(), png::EncodingError> {
+        self.check_size()?;
+        let mut encoder = png::Encoder::new(writer, self.width() as u32, self.height() as u32);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.write_header()?.write_image_data(&self.rgb_data())
+    }
+
+    /// Coloured terminal view using 24-bit colour escape codes, meant for small boards
+    pub fn ansi_preview(&self) -> io::Result<String> {
+        self.check_size()?;
+        Ok(self.rows().into_iter()
+            .map(|row| row.into_iter()
+                .map(|marks| {
+                    let [r, g, b] = self.colour(marks);
+                    let symbol = match marks {
+                        0 => ".".to_string(),
+                        1..=9 => marks.to_string(),
+                        _ => "+".to_string(),
+                    };
+                    format!("\x1b[48;2;{};{};{};37m{}\x1b[0m", r, g, b, symbol)
+                })
+                .collect::<String>())
+            .collect::<Vec<String>>()
+            .join("\n"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use crate::board::Board;
+    use crate::heatmap::{EMPTY, Heatmap, MAX_SIDE, SCALE};
+
+    fn test_board() -> Board {
+        let mut board = Board::default();
+        fs::read_to_string("test_data.dat").unwrap().lines()
+            .for_each(|line| board.mark(line.parse().unwrap()));
+        board
+    }
+
+    #[test]
+    fn colour_scale() {
+        let board = test_board();
+        let heatmap = Heatmap::new(&board);
+        assert_eq!(board.max_marks(), 3);
+        assert_eq!(heatmap.colour(0), EMPTY);
+        assert_eq!(heatmap.colour(1), SCALE[0]);
+        assert_eq!(heatmap.colour(3), SCALE[3]);
+        let highlighted = Heatmap::new(&board).highlight_at_least(2);
+        assert_eq!(highlighted.colour(1), SCALE[0].map(|value| value / 3));
+        assert_eq!(highlighted.colour(3), SCALE[3]);
+    }
+
+    #[test]
+    fn export() {
+        let board = test_board();
+        let heatmap = Heatmap::new(&board).highlight_at_least(2);
+        assert_eq!((heatmap.width(), heatmap.height()), (10, 10));
+
+        let mut ppm = vec![];
+        heatmap.write_ppm(&mut ppm).unwrap();
+        assert!(ppm.starts_with(b"P6\n10 10\n255\n"));
+        assert_eq!(ppm.len(), "P6\n10 10\n255\n".len() + 10 * 10 * 3);
+
+        let mut png = vec![];
+        heatmap.write_png(&mut png).unwrap();
+        assert!(png.starts_with(&[0x89, b'P', b'N', b'G']));
+
+        let preview = heatmap.ansi_preview().unwrap();
+        assert_eq!(preview.lines().count(), 10);
+        // Top left cell is covered once and dimmed, the one next to it is empty
+        let [r, g, b] = SCALE[0].map(|value| value / 3);
+        let first_row = preview.lines().next().unwrap();
+        assert!(first_row.starts_with(&format!("\x1b[48;2;{};{};{};37m1\x1b[0m\x1b[48;2;0;0;0;37m.\x1b[0m", r, g, b)));
+    }
+
+    #[test]
+    fn rejects_huge_extent() {
+        let mut board = test_board();
+        board.mark("0,0 -> 0,0".parse().unwrap());
+        board.mark(format!("{},{} -> {},{}", MAX_SIDE, 5, MAX_SIDE, 5).parse().unwrap());
+        let heatmap = Heatmap::new(&board);
+        assert!(heatmap.write_ppm(vec![]).is_err());
+        assert!(heatmap.write_png(vec![]).is_err());
+        assert!(heatmap.ansi_preview().is_err());
+    }
+}
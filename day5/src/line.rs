@@ -3,8 +3,8 @@ use crate::point::Point;
 
 #[derive(Debug, PartialEq, Copy, Clone)]
 pub struct Line {
-    start: Point,
-    end: Point,
+    pub(crate) start: Point,
+    pub(crate) end: Point,
 }
 
 impl FromStr for Line {
@@ -31,33 +31,178 @@ impl Line {
     }
 
     pub fn is_diagonal(&self) -> bool {
-        let inc  = self.start - self.end;
-        inc.x.abs() == inc.y.abs()
+        let (dx, dy) = self.start.delta(self.end);
+        dx.abs() == dy.abs()
     }
     pub fn is_horizontal_vertical_or_diagonal(&self) -> bool {
         self.is_horizontal_or_vertical() || self.is_diagonal()
     }
+    /// Points covered by line, lines that are not horizontal, vertical or diagonal use Bresenham
     pub fn point_vec(&self) -> Vec<Point> {
+        self.rasterize(Rasterization::Bresenham)
+    }
+    pub fn rasterize(&self, rasterization: Rasterization) -> Vec<Point> {
         if self.is_horizontal_vertical_or_diagonal() {
-            // println!("Start: {:?} | End: {:?}",self.start, self.end);
-            let inc = (self.end - self.start).normalize();
-            // println!("Inc: {:?}",inc);
-            let mut points = vec![self.start.clone()];
+            let inc = self.start.step_towards(self.end);
+            let mut points = vec![self.start];
             while points.last().unwrap() != &self.end {
                 points.push(*points.last().unwrap() + inc);
-                // dbg!(&points);
             }
             points
         } else {
-            panic!()
+            match rasterization {
+                Rasterization::Bresenham => self.bresenham(),
+                Rasterization::Supercover => self.supercover(),
+            }
+        }
+    }
+    fn bresenham(&self) -> Vec<Point> {
+        let step = self.start.step_towards(self.end);
+        let (dx, dy) = self.end.delta(self.start);
+        let (dx, dy) = (dx.abs(), -dy.abs());
+        let mut err = dx + dy;
+        let mut current = self.start;
+        let mut points = vec![current];
+        while current != self.end {
+            let doubled_err = 2 * err;
+            if doubled_err >= dy {
+                err += dy;
+                current.x += step.x;
+            }
+            if doubled_err <= dx {
+                err += dx;
+                current.y += step.y;
+            }
+            points.push(current);
         }
+        points
+    }
+    /// Every point whose unit square is touched by the line, both neighbours are taken when crossing a corner
+    fn supercover(&self) -> Vec<Point> {
+        let step = self.start.step_towards(self.end);
+        let (nx, ny) = self.end.delta(self.start);
+        let (nx, ny) = (nx.abs(), ny.abs());
+        let (mut ix, mut iy) = (0, 0);
+        let mut current = self.start;
+        let mut points = vec![current];
+        while ix < nx || iy < ny {
+            let decision = (1 + 2 * ix) * ny - (1 + 2 * iy) * nx;
+            if decision == 0 {
+                points.push(Point { x: current.x + step.x, y: current.y });
+                points.push(Point { x: current.x, y: current.y + step.y });
+                current = current + step;
+                ix += 1;
+                iy += 1;
+            } else if decision < 0 {
+                current.x += step.x;
+                ix += 1;
+            } else {
+                current.y += step.y;
+                iy += 1;
+            }
+            points.push(current);
+        }
+        points
     }
 }
 
+/// Geometry on exact segments, independent of rasterization
+impl Line {
+    /// Whether point lies exactly on the segment
+    pub fn contains(&self, point: Point) -> bool {
+        let (r, q) = (self.end.delta(self.start), point.delta(self.start));
+        cross(r, q) == 0
+            && point.x >= self.start.x.min(self.end.x) && point.x <= self.start.x.max(self.end.x)
+            && point.y >= self.start.y.min(self.end.y) && point.y <= self.start.y.max(self.end.y)
+    }
+
+    pub fn intersection(&self, other: &Line) -> Option<Intersection> {
+        let (p, r) = (self.start, self.end.delta(self.start));
+        let (q, s) = (other.start, other.end.delta(other.start));
+        if r == (0, 0) {
+            return other.contains(p).then_some(Intersection::Crossing { x: p.x as f64, y: p.y as f64 });
+        }
+        if s == (0, 0) {
+            return self.contains(q).then_some(Intersection::Crossing { x: q.x as f64, y: q.y as f64 });
+        }
+        let mut denominator = cross(r, s);
+        let (mut t, mut u) = (cross(q.delta(p), s), cross(q.delta(p), r));
+        if denominator == 0 {
+            if u != 0 {
+                return None;
+            }
+            // Collinear, overlap is limited by two of the endpoints
+            let position = |point: Point| dot(point.delta(p), r);
+            let mut endpoints = [self.start, self.end, other.start, other.end];
+            endpoints.sort_by_key(|point| position(*point));
+            let (from, to) = (endpoints[1], endpoints[2]);
+            let (self_range, other_range) = ((0, dot(r, r)), sorted(position(q), position(other.end)));
+            if self_range.1 < other_range.0 || other_range.1 < self_range.0 {
+                return None;
+            }
+            return Some(if from == to {
+                Intersection::Crossing { x: from.x as f64, y: from.y as f64 }
+            } else {
+                Intersection::Overlap(Line { start: from, end: to })
+            });
+        }
+        if denominator < 0 {
+            denominator = -denominator;
+            t = -t;
+            u = -u;
+        }
+        if t < 0 || t > denominator || u < 0 || u > denominator {
+            return None;
+        }
+        let fraction = t as f64 / denominator as f64;
+        Some(Intersection::Crossing { x: p.x as f64 + r.0 as f64 * fraction, y: p.y as f64 + r.1 as f64 * fraction })
+    }
+
+    /// Euclidean distance from point to the closest point of the segment
+    pub fn distance_to(&self, point: Point) -> f64 {
+        let (r, q) = (self.end.delta(self.start), point.delta(self.start));
+        let length = dot(r, r);
+        let fraction = if length == 0 { 0.0 } else { (dot(q, r) as f64 / length as f64).clamp(0.0, 1.0) };
+        let dx = q.0 as f64 - r.0 as f64 * fraction;
+        let dy = q.1 as f64 - r.1 as f64 * fraction;
+        dx.hypot(dy)
+    }
+}
+
+/// Products of point differences need more than 64 bits
+fn cross(a: (i64, i64), b: (i64, i64)) -> i128 {
+    a.0 as i128 * b.1 as i128 - a.1 as i128 * b.0 as i128
+}
+
+fn dot(a: (i64, i64), b: (i64, i64)) -> i128 {
+    a.0 as i128 * b.0 as i128 + a.1 as i128 * b.1 as i128
+}
+
+fn sorted(a: i128, b: i128) -> (i128, i128) {
+    (a.min(b), a.max(b))
+}
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum Intersection {
+    /// Lines meet at a single point, not necessarily a lattice one
+    Crossing { x: f64, y: f64 },
+    /// Collinear lines share a segment
+    Overlap(Line),
+}
+
+/// Algorithm used for lines that are not horizontal, vertical or diagonal
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum Rasterization {
+    /// One point per step along the major axis
+    Bresenham,
+    /// All points touched by the line
+    Supercover,
+}
+
 #[cfg(test)]
 mod line_tests {
     use crate::point::Point;
-    use crate::line::Line;
+    use crate::line::{Intersection, Line, Rasterization};
 
     #[test]
     fn parse_line_str() {
@@ -81,4 +226,65 @@ mod line_tests {
         let line = "9,7 -> 7,7".parse::<Line>().unwrap();
         line.point_vec();
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn arbitrary_slope() {
+        let line = "0,0 -> 4,2".parse::<Line>().unwrap();
+        assert_eq!(line.point_vec(), vec![
+            Point { x: 0, y: 0 },
+            Point { x: 1, y: 1 },
+            Point { x: 2, y: 1 },
+            Point { x: 3, y: 2 },
+            Point { x: 4, y: 2 },
+        ]);
+        assert_eq!(line.rasterize(Rasterization::Supercover), vec![
+            Point { x: 0, y: 0 },
+            Point { x: 1, y: 0 },
+            Point { x: 1, y: 1 },
+            Point { x: 2, y: 1 },
+            Point { x: 3, y: 1 },
+            Point { x: 3, y: 2 },
+            Point { x: 4, y: 2 },
+        ]);
+        let steep = "3,7 -> 1,0".parse::<Line>().unwrap();
+        let points = steep.point_vec();
+        assert_eq!(points.len(), 8);
+        assert_eq!(points.first(), Some(&Point { x: 3, y: 7 }));
+        assert_eq!(points.last(), Some(&Point { x: 1, y: 0 }));
+        let points = steep.rasterize(Rasterization::Supercover);
+        assert_eq!(points.len(), 10);
+        assert_eq!(points.last(), Some(&Point { x: 1, y: 0 }));
+        let corner = "0,0 -> 2,6".parse::<Line>().unwrap();
+        assert_eq!(corner.rasterize(Rasterization::Supercover)[..5], [
+            Point { x: 0, y: 0 },
+            Point { x: 0, y: 1 },
+            Point { x: 1, y: 1 },
+            Point { x: 0, y: 2 },
+            Point { x: 1, y: 2 },
+        ]);
+    }
+
+    #[test]
+    fn geometry() {
+        let line = "0,0 -> 4,2".parse::<Line>().unwrap();
+        assert!(line.contains(Point { x: 2, y: 1 }));
+        assert!(!line.contains(Point { x: 1, y: 1 }));
+        assert!(!line.contains(Point { x: 6, y: 3 }));
+        assert_eq!(line.distance_to(Point { x: 2, y: 1 }), 0.0);
+        assert_eq!(line.distance_to(Point { x: 7, y: 6 }), 5.0);
+        assert_eq!(line.intersection(&"0,2 -> 2,0".parse().unwrap()), Some(Intersection::Crossing { x: 4.0 / 3.0, y: 2.0 / 3.0 }));
+        assert_eq!(line.intersection(&"0,1 -> 4,3".parse().unwrap()), None);
+        assert_eq!(line.intersection(&"5,0 -> 5,9".parse().unwrap()), None);
+        assert_eq!(line.intersection(&"8,4 -> 2,1".parse().unwrap()), Some(Intersection::Overlap("2,1 -> 4,2".parse().unwrap())));
+        assert_eq!(line.intersection(&"4,2 -> 6,3".parse().unwrap()), Some(Intersection::Crossing { x: 4.0, y: 2.0 }));
+        assert_eq!(line.intersection(&"6,3 -> 8,4".parse().unwrap()), None);
+        assert_eq!(line.intersection(&"2,1 -> 2,1".parse().unwrap()), Some(Intersection::Crossing { x: 2.0, y: 1.0 }));
+        // Differences and their products do not fit in 32 and 64 bits
+        let wide = "-2000000000,-2000000000 -> 2000000000,2000000000".parse::<Line>().unwrap();
+        assert!(wide.is_diagonal());
+        assert!(wide.contains(Point { x: 1999999999, y: 1999999999 }));
+        assert_eq!(wide.intersection(&"-2000000000,2000000000 -> 2000000000,-2000000000".parse().unwrap()),
+                   Some(Intersection::Crossing { x: 0.0, y: 0.0 }));
+        assert_eq!(wide.distance_to(Point { x: -2000000000, y: 2000000000 }), 2000000000.0 * 2f64.sqrt());
+    }
+}
@@ -1,16 +1,24 @@
 use std::fs::File;
+use std::io;
 use std::io::{BufRead, BufReader};
-use board::Board;
-use crate::line::Line;
+use board::{Board, BoundingBox};
+use crate::heatmap::Heatmap;
+use crate::line::{Line, Rasterization};
+use crate::overlaps::OverlapEngine;
+use crate::point::Point;
+use crate::spatial::LineIndex;
 
 mod line;
 mod point;
 mod board;
+mod overlaps;
+mod heatmap;
+mod spatial;
 
 fn main() {
     if let Ok(file) = File::open("day5/input_data.dat") {
         let reader = BufReader::new(file);
-        let mut board = Board::with_dim(1000);
+        let mut board = Board::default();
         reader.lines()
             .map(|str| str.unwrap().parse::<Line>().unwrap())
             .filter(|line|line.is_horizontal_or_vertical())
@@ -19,12 +27,42 @@ fn main() {
     }
     if let Ok(file) = File::open("day5/input_data.dat") {
         let reader = BufReader::new(file);
-        let mut board = Board::with_dim(1000);
+        let mut board = Board::default();
         reader.lines()
             .map(|str| str.unwrap().parse::<Line>().unwrap())
             .filter(|line|line.is_horizontal_vertical_or_diagonal())
             .for_each(|line|board.mark(line));
-        println!("Part2 marked points count: {}", board.marked_at_least(2))
+        println!("Part2 marked points count: {}", board.marked_at_least(2));
+        // Optional argument: output path, `.ppm` writes a PPM and `-` prints a terminal preview
+        let heatmap = Heatmap::new(&board).highlight_at_least(2);
+        let output = std::env::args().nth(1).unwrap_or_else(|| "day5/heatmap.png".to_string());
+        let written = match output.as_str() {
+            "-" => heatmap.ansi_preview().map(|preview| println!("{}", preview)),
+            path if path.ends_with(".ppm") => File::create(path).and_then(|image| heatmap.write_ppm(image)),
+            path => File::create(path).and_then(|image| heatmap.write_png(image).map_err(io::Error::from)),
+        };
+        if let Err(err) = written {
+            println!("Heatmap not written: {}", err);
+        }
+    }
+    if let Ok(file) = File::open("day5/input_data.dat") {
+        let reader = BufReader::new(file);
+        let lines: Vec<Line> = reader.lines()
+            .map(|str| str.unwrap().parse::<Line>().unwrap())
+            .collect();
+        println!("Overlap engine points covered at least twice: {}", OverlapEngine::new(&lines).count_at_least(2));
+        let mut supercover = Board::default();
+        lines.iter().for_each(|line| supercover.mark_with(*line, Rasterization::Supercover));
+        println!("Points touched by any line (supercover): {}", supercover.marked_at_least(1));
+        let index = LineIndex::new(lines, 64);
+        let centre = Point { x: 500, y: 500 };
+        println!("Lines through {:?}: {}", centre, index.lines_through(centre).len());
+        let area = BoundingBox { min: Point { x: 0, y: 0 }, max: Point { x: 99, y: 99 } };
+        println!("Lines within {:?}: {}", area, index.lines_in_rectangle(area).len());
+        if let Some((line, distance)) = index.closest_line(Point { x: 0, y: 0 }) {
+            println!("Line closest to origin: {} at {:.2}, meets {} other lines",
+                     line, distance, index.intersections(line).len());
+        }
     }
 }
 
@@ -39,7 +77,7 @@ mod main_test{
     fn with_test_data() {
         if let Ok(file) = File::open("test_data.dat") {
             let reader = BufReader::new(file);
-            let mut board = Board::with_dim(10);
+            let mut board = Board::default();
             reader.lines()
                 .map(|str| str.unwrap().parse::<Line>().unwrap())
                 .filter(|line|line.is_horizontal_or_vertical())
@@ -51,7 +89,7 @@ mod main_test{
     fn part2_with_test_data() {
         if let Ok(file) = File::open("test_data.dat") {
             let reader = BufReader::new(file);
-            let mut board = Board::with_dim(10);
+            let mut board = Board::default();
             reader.lines()
                 .map(|str| str.unwrap().parse::<Line>().unwrap())
                 .filter(|line|line.is_horizontal_vertical_or_diagonal())
@@ -0,0 +1,295 @@
+use std::collections::HashMap;
+use crate::line::Line;
+
+/// Infinite line through lattice points, direction `(dx, dy)` is primitive and points right (or up when vertical).
+///
+/// Offsets and positions are products of coordinates and directions, they take 128 bits
+/// for lines spanning the whole 32 bit range.
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
+struct LineKey {
+    dx: i64,
+    dy: i64,
+    /// `dx * y - dy * x`, same for every point on the line
+    offset: i128,
+}
+
+impl LineKey {
+    fn step(&self) -> i128 {
+        let (dx, dy) = (self.dx as i128, self.dy as i128);
+        dx * dx + dy * dy
+    }
+    /// Position along the line, consecutive lattice points differ by `step`
+    fn position(&self, (x, y): (i64, i64)) -> i128 {
+        x as i128 * self.dx as i128 + y as i128 * self.dy as i128
+    }
+    fn point_at(&self, anchor: (i64, i64), position: i128) -> (i64, i64) {
+        let steps = (position - self.position(anchor)) / self.step();
+        ((anchor.0 as i128 + steps * self.dx as i128) as i64, (anchor.1 as i128 + steps * self.dy as i128) as i64)
+    }
+    /// Lattice point both lines pass through, None for parallel lines or points outside 64 bits
+    fn intersection(&self, other: &LineKey) -> Option<(i64, i64)> {
+        let (dx, dy, other_dx, other_dy) = (self.dx as i128, self.dy as i128, other.dx as i128, other.dy as i128);
+        let det = dx * other_dy - other_dx * dy;
+        if det == 0 {
+            return None;
+        }
+        let x_num = self.offset * other_dx - dx * other.offset;
+        let y_num = other_dy * self.offset - dy * other.offset;
+        if x_num % det != 0 || y_num % det != 0 {
+            return None;
+        }
+        Some(((x_num / det).try_into().ok()?, (y_num / det).try_into().ok()?))
+    }
+}
+
+/// Offset of the line with direction `(dx, dy)` through point
+fn offset((dx, dy): (i64, i64), (x, y): (i64, i64)) -> i128 {
+    dx as i128 * y as i128 - dy as i128 * x as i128
+}
+
+/// Part of a line covered by the same number of segments, `from` and `to` are inclusive positions
+#[derive(Debug, Copy, Clone)]
+struct Run {
+    group: usize,
+    from: i128,
+    to: i128,
+    coverage: u32,
+}
+
+#[derive(Debug, Clone)]
+struct Group {
+    key: LineKey,
+    anchor: (i64, i64),
+    runs: Vec<Run>,
+}
+
+impl Group {
+    fn point_at(&self, position: i128) -> (i64, i64) {
+        self.key.point_at(self.anchor, position)
+    }
+    fn len(&self, run: &Run) -> usize {
+        ((run.to - run.from) / self.key.step()) as usize + 1
+    }
+}
+
+type Segments = ((i64, i64), Vec<(i128, i128)>);
+/// Direction and runs with the offset of their line
+type DirectionRuns = ((i64, i64), Vec<(i128, Run)>);
+
+/// Counts lattice points covered by lines without rasterizing them.
+///
+/// Segments lying on the same infinite line are merged with a 1D sweep into runs of equal coverage,
+/// then runs of every other direction whose lines pass through a run are looked up by line offset
+/// to find crossings, parallel runs are never compared. Only lattice points lying
+/// exactly on a segment are counted, which for horizontal, vertical and diagonal lines
+/// are the same points `Board` marks.
+#[derive(Debug, Clone)]
+pub struct OverlapEngine {
+    groups: Vec<Group>,
+    /// Crossings of different lines with coverage contributed by each line
+    crossings: HashMap<(i64, i64), HashMap<usize, u32>>,
+}
+
+impl OverlapEngine {
+    pub fn new(lines: &[Line]) -> Self {
+        let groups = Self::group_lines(lines);
+        let crossings = Self::find_crossings(&groups);
+        OverlapEngine { groups, crossings }
+    }
+
+    fn group_lines(lines: &[Line]) -> Vec<Group> {
+        // Anchor point and position intervals of segments on every line
+        let mut segments: HashMap<LineKey, Segments> = HashMap::new();
+        for line in lines {
+            let start = (line.start.x as i64, line.start.y as i64);
+            let end = (line.end.x as i64, line.end.y as i64);
+            let (mut dx, mut dy) = (end.0 - start.0, end.1 - start.1);
+            let divisor = gcd(dx.abs(), dy.abs());
+            if divisor == 0 {
+                // Single point, treated as a horizontal segment of length 0
+                dx = 1;
+            } else {
+                dx /= divisor;
+                dy /= divisor;
+            }
+            if dx < 0 || (dx == 0 && dy < 0) {
+                dx = -dx;
+                dy = -dy;
+            }
+            let key = LineKey { dx, dy, offset: offset((dx, dy), start) };
+            let (from, to) = (key.position(start), key.position(end));
+            segments.entry(key)
+                .or_insert_with(|| (start, vec![]))
+                .1.push((from.min(to), from.max(to)));
+        }
+        segments.into_iter().enumerate()
+            .map(|(group, (key, (anchor, intervals)))| Group {
+                key,
+                anchor,
+                runs: Self::merge_collinear(group, key.step(), intervals),
+            })
+            .collect()
+    }
+
+    /// 1D sweep turning overlapping intervals into runs of constant coverage
+    fn merge_collinear(group: usize, step: i128, intervals: Vec<(i128, i128)>) -> Vec<Run> {
+        let mut events: Vec<(i128, i32)> = intervals.into_iter()
+            .flat_map(|(from, to)| [(from, 1), (to + step, -1)])
+            .collect();
+        events.sort_unstable();
+        let mut runs = vec![];
+        let mut coverage = 0i32;
+        for (idx, (position, change)) in events.iter().enumerate() {
+            coverage += change;
+            match events.get(idx + 1) {
+                Some((next, _)) if next > position && coverage > 0 => runs.push(Run {
+                    group,
+                    from: *position,
+                    to: next - step,
+                    coverage: coverage as u32,
+                }),
+                _ => {}
+            }
+        }
+        runs
+    }
+
+    /// Runs are bucketed by direction and sorted by line offset, for every run only runs of other
+    /// directions whose lines pass between its endpoints are checked for a lattice crossing
+    fn find_crossings(groups: &[Group]) -> HashMap<(i64, i64), HashMap<usize, u32>> {
+        let mut by_direction: HashMap<(i64, i64), Vec<(i128, Run)>> = HashMap::new();
+        groups.iter()
+            .flat_map(|group| group.runs.iter().map(move |run| ((group.key.dx, group.key.dy), (group.key.offset, *run))))
+            .for_each(|(direction, run)| by_direction.entry(direction).or_default().push(run));
+        let mut directions: Vec<DirectionRuns> = by_direction.into_iter().collect();
+        directions.iter_mut().for_each(|(_, runs)| runs.sort_unstable_by_key(|(offset, _)| *offset));
+        let mut crossings: HashMap<(i64, i64), HashMap<usize, u32>> = HashMap::new();
+        for (idx, (_, runs)) in directions.iter().enumerate() {
+            for ((dx, dy), others) in &directions[idx + 1..] {
+                for (_, run) in runs {
+                    let group = &groups[run.group];
+                    // Offsets of lines with direction (dx, dy) through both ends of the run
+                    let (start, end) = (group.point_at(run.from), group.point_at(run.to));
+                    let (start, end) = (offset((*dx, *dy), start), offset((*dx, *dy), end));
+                    let first = others.partition_point(|(offset, _)| *offset < start.min(end));
+                    for (_, other) in others[first..].iter().take_while(|(offset, _)| *offset <= start.max(end)) {
+                        let other_group = &groups[other.group];
+                        if let Some(point) = group.key.intersection(&other_group.key) {
+                            if contains(group, run, point) && contains(other_group, other, point) {
+                                let entry = crossings.entry(point).or_default();
+                                entry.insert(run.group, run.coverage);
+                                entry.insert(other.group, other.coverage);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        crossings
+    }
+
+    fn total_at(coverages: &HashMap<usize, u32>) -> u32 {
+        coverages.values().sum()
+    }
+
+    /// Number of lattice points covered by at least `n` lines
+    pub fn count_at_least(&self, n: u32) -> usize {
+        let n = n.max(1);
+        let along_lines: usize = self.groups.iter()
+            .flat_map(|group| group.runs.iter()
+                .filter(|run| run.coverage >= n)
+                .map(move |run| group.len(run)))
+            .sum();
+        let crossings_counted_along_lines: usize = self.crossings.values()
+            .map(|coverages| coverages.values().filter(|coverage| **coverage >= n).count())
+            .sum();
+        let crossings = self.crossings.values()
+            .filter(|coverages| Self::total_at(coverages) >= n)
+            .count();
+        along_lines - crossings_counted_along_lines + crossings
+    }
+}
+
+fn contains(group: &Group, run: &Run, point: (i64, i64)) -> bool {
+    let position = group.key.position(point);
+    run.from <= position && position <= run.to
+}
+
+fn gcd(a: i64, b: i64) -> i64 {
+    if b == 0 { a } else { gcd(b, a % b) }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use crate::board::Board;
+    use crate::line::Line;
+    use crate::overlaps::OverlapEngine;
+
+    fn cross_check(lines: &[Line]) {
+        let mut board = Board::default();
+        lines.iter().for_each(|line| board.mark(*line));
+        let engine = OverlapEngine::new(lines);
+        for n in 1..=4 {
+            assert_eq!(engine.count_at_least(n), board.marked_at_least(n), "at least {}", n);
+        }
+    }
+
+    #[test]
+    fn with_test_data() {
+        let lines: Vec<Line> = fs::read_to_string("test_data.dat").unwrap()
+            .lines().map(|line| line.parse().unwrap()).collect();
+        let engine = OverlapEngine::new(&lines);
+        assert_eq!(engine.count_at_least(2), 12);
+        cross_check(&lines);
+        let straight: Vec<Line> = lines.into_iter().filter(|line| line.is_horizontal_or_vertical()).collect();
+        assert_eq!(OverlapEngine::new(&straight).count_at_least(2), 5);
+    }
+
+    #[test]
+    fn collinear_overlaps() {
+        let lines: Vec<Line> = ["0,0 -> 6,0", "3,0 -> 9,0", "5,0 -> 4,0", "0,0 -> 6,6", "2,2 -> 4,4",
+            "4,0 -> 4,8", "7,7 -> 7,7", "9,0 -> 0,9", "1,0 -> 5,2"].iter()
+            .map(|line| line.parse().unwrap())
+            .collect();
+        let engine = OverlapEngine::new(&lines);
+        // "1,0 -> 5,2" covers lattice points (1,0) (3,1) (5,2) only
+        let exact: Vec<Line> = lines.iter().take(8).copied()
+            .chain(["1,0 -> 1,0", "3,1 -> 3,1", "5,2 -> 5,2"].iter().map(|line| line.parse().unwrap()))
+            .collect();
+        for n in 1..=4 {
+            assert_eq!(engine.count_at_least(n), OverlapEngine::new(&exact).count_at_least(n));
+        }
+        cross_check(&exact);
+    }
+
+    #[test]
+    fn large_coordinates() {
+        let lines: Vec<Line> = ["0,0 -> 1999999999,1999999998", "0,1999999998 -> 1999999997,0", "5,7 -> 1999999991,1999999990",
+            "-2147483648,-2147483648 -> 2147483647,2147483647", "2147483647,-2147483648 -> -2147483648,2147483647"].iter()
+            .map(|line| line.parse().unwrap())
+            .collect();
+        let engine = OverlapEngine::new(&lines);
+        // Sloped lines hit the first diagonal at (0, 0) and (1333333329, 1333333329), the diagonals do not meet on the lattice
+        assert_eq!(engine.count_at_least(2), 2);
+        assert_eq!(engine.count_at_least(3), 0);
+        assert_eq!(engine.count_at_least(1), 2 * (1 << 32) + 6);
+    }
+
+    #[test]
+    fn long_parallel_runs() {
+        let lines: Vec<Line> = (0..100).map(|y| format!("0,{} -> 500,{}", y, y))
+            .chain((0..5).map(|x| format!("{},0 -> {},150", x * 100, x * 100)))
+            .chain(["0,0 -> 150,150".to_string(), "0,99 -> 99,0".to_string()])
+            .map(|line| line.parse().unwrap())
+            .collect();
+        cross_check(&lines);
+    }
+
+    #[test]
+    fn with_input_data() {
+        let lines: Vec<Line> = fs::read_to_string("input_data.dat").unwrap()
+            .lines().map(|line| line.parse().unwrap()).collect();
+        cross_check(&lines);
+    }
+}
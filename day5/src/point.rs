@@ -1,18 +1,20 @@
 use std::ops::{Add, Sub};
 use std::str::FromStr;
 
-#[derive(Debug, PartialEq, Copy, Clone)]
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
 pub struct Point {
     pub(crate) x: i32,
     pub(crate) y: i32,
 }
 
 impl Point {
-    pub fn normalize(self) -> Self {
-        Self {
-            x: if self.x != 0 { self.x/self.x.abs() } else { 0 },
-            y: if self.y != 0 { self.y/self.y.abs() } else { 0 },
-        }
+    /// `self - other` widened so that any two points can be subtracted
+    pub fn delta(self, other: Point) -> (i64, i64) {
+        (self.x as i64 - other.x as i64, self.y as i64 - other.y as i64)
+    }
+    /// Unit step from `self` towards `other` along both axes
+    pub fn step_towards(self, other: Point) -> Point {
+        Point { x: other.x.cmp(&self.x) as i32, y: other.y.cmp(&self.y) as i32 }
     }
 }
 
@@ -0,0 +1,257 @@
+use std::collections::{BTreeSet, HashMap};
+use crate::board::BoundingBox;
+use crate::line::{Intersection, Line};
+use crate::point::Point;
+
+/// Grid bucketing of lines, every line is stored in all square buckets it passes through.
+///
+/// Bucket `(x, y)` is the closed square from `(x, y) * bucket_size` to `(x + 1, y + 1) * bucket_size`,
+/// so a sloped line passing between lattice points of two buckets is stored in both.
+#[derive(Debug, Clone)]
+pub struct LineIndex {
+    lines: Vec<Line>,
+    bucket_size: i32,
+    buckets: HashMap<(i32, i32), Vec<usize>>,
+    /// Buckets every line is stored in
+    line_buckets: Vec<Vec<(i32, i32)>>,
+    /// Bucket coordinates of all buckets that hold at least one line
+    extent: Option<BoundingBox>,
+}
+
+impl LineIndex {
+    pub fn new(lines: Vec<Line>, bucket_size: i32) -> Self {
+        assert!(bucket_size > 0, "Bucket size has to be positive");
+        let line_buckets = vec![vec![]; lines.len()];
+        let mut index = LineIndex { lines, bucket_size, buckets: HashMap::new(), line_buckets, extent: None };
+        for idx in 0..index.lines.len() {
+            let line = index.lines[idx];
+            let area = BoundingBox {
+                min: Point { x: line.start.x.min(line.end.x), y: line.start.y.min(line.end.y) },
+                max: Point { x: line.start.x.max(line.end.x), y: line.start.y.max(line.end.y) },
+            };
+            for bucket in index.buckets_covering(area) {
+                if intersects_rectangle(&line, index.bucket_square(bucket)) {
+                    index.buckets.entry(bucket).or_default().push(idx);
+                    index.line_buckets[idx].push(bucket);
+                    let point = Point { x: bucket.0, y: bucket.1 };
+                    index.extent = Some(match index.extent {
+                        None => BoundingBox { min: point, max: point },
+                        Some(extent) => BoundingBox {
+                            min: Point { x: extent.min.x.min(point.x), y: extent.min.y.min(point.y) },
+                            max: Point { x: extent.max.x.max(point.x), y: extent.max.y.max(point.y) },
+                        },
+                    });
+                }
+            }
+        }
+        index
+    }
+
+    fn bucket_of(&self, point: Point) -> (i32, i32) {
+        (point.x.div_euclid(self.bucket_size), point.y.div_euclid(self.bucket_size))
+    }
+    /// Real square of bucket, shares its borders with neighbouring buckets
+    fn bucket_square(&self, (x, y): (i32, i32)) -> BoundingBox {
+        BoundingBox {
+            min: Point { x: x * self.bucket_size, y: y * self.bucket_size },
+            max: Point { x: (x + 1) * self.bucket_size, y: (y + 1) * self.bucket_size },
+        }
+    }
+    fn buckets_covering(&self, area: BoundingBox) -> Vec<(i32, i32)> {
+        let (min, max) = (self.bucket_of(area.min), self.bucket_of(area.max));
+        (min.0..=max.0)
+            .flat_map(|x| (min.1..=max.1).map(move |y| (x, y)))
+            .collect()
+    }
+    fn candidates(&self, buckets: impl IntoIterator<Item=(i32, i32)>) -> BTreeSet<usize> {
+        buckets.into_iter()
+            .filter_map(|bucket| self.buckets.get(&bucket))
+            .flatten()
+            .copied()
+            .collect()
+    }
+
+    /// Indices of lines passing exactly through point
+    pub fn lines_through(&self, point: Point) -> Vec<usize> {
+        self.candidates([self.bucket_of(point)]).into_iter()
+            .filter(|idx| self.lines[*idx].contains(point))
+            .collect()
+    }
+
+    /// Indices of lines with at least one point inside rectangle (borders included)
+    pub fn lines_in_rectangle(&self, rectangle: BoundingBox) -> Vec<usize> {
+        self.candidates(self.buckets_covering(rectangle)).into_iter()
+            .filter(|idx| intersects_rectangle(&self.lines[*idx], rectangle))
+            .collect()
+    }
+
+    /// Lines meeting line with given index and where they meet it
+    pub fn intersections(&self, idx: usize) -> Vec<(usize, Intersection)> {
+        self.candidates(self.line_buckets[idx].iter().copied()).into_iter()
+            .filter(|other| *other != idx)
+            .filter_map(|other| self.lines[idx].intersection(&self.lines[other]).map(|intersection| (other, intersection)))
+            .collect()
+    }
+
+    /// Buckets at Chebyshev distance `ring` from `centre` that lie within the extent, side by side
+    fn ring(&self, centre: (i64, i64), ring: i64, extent: BoundingBox) -> Vec<(i32, i32)> {
+        let (min_x, max_x) = (extent.min.x as i64, extent.max.x as i64);
+        let (min_y, max_y) = (extent.min.y as i64, extent.max.y as i64);
+        let columns = |y: i64| (min_x.max(centre.0 - ring)..=max_x.min(centre.0 + ring))
+            .filter(move |_| (min_y..=max_y).contains(&y))
+            .map(move |x| (x as i32, y as i32));
+        let rows = |x: i64| (min_y.max(centre.1 - ring + 1)..=max_y.min(centre.1 + ring - 1))
+            .filter(move |_| (min_x..=max_x).contains(&x))
+            .map(move |y| (x as i32, y as i32));
+        if ring == 0 {
+            return columns(centre.1).filter(|bucket| bucket.0 as i64 == centre.0).collect();
+        }
+        columns(centre.1 - ring).chain(columns(centre.1 + ring))
+            .chain(rows(centre.0 - ring)).chain(rows(centre.0 + ring))
+            .collect()
+    }
+
+    /// Index of and distance to the line closest to point, searched in growing rings of buckets.
+    ///
+    /// Rings start at the first one reaching the extent and only their buckets inside the extent are visited.
+    pub fn closest_line(&self, point: Point) -> Option<(usize, f64)> {
+        let extent = self.extent?;
+        let centre = self.bucket_of(point);
+        let centre = (centre.0 as i64, centre.1 as i64);
+        let gap = |value: i64, min: i32, max: i32| (min as i64 - value).max(value - max as i64).max(0);
+        let first_ring = gap(centre.0, extent.min.x, extent.max.x).max(gap(centre.1, extent.min.y, extent.max.y));
+        let last_ring = [extent.min.x, extent.max.x].iter().map(|x| (*x as i64 - centre.0).abs())
+            .chain([extent.min.y, extent.max.y].iter().map(|y| (*y as i64 - centre.1).abs()))
+            .max()
+            .unwrap();
+        let mut closest: Option<(usize, f64)> = None;
+        for ring in first_ring..=last_ring {
+            for idx in self.candidates(self.ring(centre, ring, extent)) {
+                let distance = self.lines[idx].distance_to(point);
+                if closest.is_none_or(|(_, best)| distance < best) {
+                    closest = Some((idx, distance));
+                }
+            }
+            // Lines outside the squares of rings so far are at least `ring * bucket_size` away
+            match closest {
+                Some((_, best)) if best <= ring as f64 * self.bucket_size as f64 => break,
+                _ => {}
+            }
+        }
+        closest
+    }
+}
+
+/// Segment touches rectangle if it overlaps its bounding box and corners are not all on one side
+fn intersects_rectangle(line: &Line, rectangle: BoundingBox) -> bool {
+    if line.start.x.max(line.end.x) < rectangle.min.x || line.start.x.min(line.end.x) > rectangle.max.x
+        || line.start.y.max(line.end.y) < rectangle.min.y || line.start.y.min(line.end.y) > rectangle.max.y {
+        return false;
+    }
+    let side = |corner: Point| {
+        let (r, q) = (line.end - line.start, corner - line.start);
+        (r.x as i64 * q.y as i64 - r.y as i64 * q.x as i64).signum()
+    };
+    let sides: Vec<i64> = [
+        rectangle.min,
+        rectangle.max,
+        Point { x: rectangle.min.x, y: rectangle.max.y },
+        Point { x: rectangle.max.x, y: rectangle.min.y },
+    ].into_iter().map(side).collect();
+    !(sides.iter().all(|side| *side > 0) || sides.iter().all(|side| *side < 0))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use crate::board::{Board, BoundingBox};
+    use crate::line::{Intersection, Line};
+    use crate::point::Point;
+    use crate::spatial::{intersects_rectangle, LineIndex};
+
+    fn test_lines() -> Vec<Line> {
+        fs::read_to_string("test_data.dat").unwrap()
+            .lines().map(|line| line.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn lines_through_point() {
+        let lines = test_lines();
+        let index = LineIndex::new(lines.clone(), 3);
+        assert_eq!(index.lines_through(Point { x: 7, y: 4 }), vec![2, 4]);
+        let mut board = Board::default();
+        lines.iter().for_each(|line| board.mark(*line));
+        for x in -1..=10 {
+            for y in -1..=10 {
+                let point = Point { x, y };
+                assert_eq!(index.lines_through(point).len() as u32, board.marks_at(point));
+            }
+        }
+    }
+
+    #[test]
+    fn lines_in_rectangle() {
+        let lines = test_lines();
+        let index = LineIndex::new(lines.clone(), 4);
+        let corner = BoundingBox { min: Point { x: 0, y: 0 }, max: Point { x: 1, y: 1 } };
+        assert_eq!(index.lines_in_rectangle(corner), vec![8]);
+        // Passes between lattice points of buckets (0, 0) and (0, -1) without touching any of them
+        let steep = LineIndex::new(vec!["1,-3 -> 2,3".parse().unwrap()], 2);
+        let rectangle = BoundingBox { min: Point { x: 1, y: 0 }, max: Point { x: 2, y: 1 } };
+        assert!(intersects_rectangle(&steep.lines[0], rectangle));
+        assert_eq!(steep.lines_in_rectangle(rectangle), vec![0]);
+        for size in 0..4 {
+            for x in -2..10 {
+                for y in -2..10 {
+                    let rectangle = BoundingBox { min: Point { x, y }, max: Point { x: x + size, y: y + size } };
+                    let expected: Vec<usize> = (0..lines.len())
+                        .filter(|idx| intersects_rectangle(&lines[*idx], rectangle))
+                        .collect();
+                    assert_eq!(index.lines_in_rectangle(rectangle), expected);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn closest_line() {
+        let lines = test_lines();
+        let index = LineIndex::new(lines.clone(), 2);
+        assert_eq!(LineIndex::new(vec![], 2).closest_line(Point { x: 0, y: 0 }), None);
+        assert_eq!(index.closest_line(Point { x: 2, y: 2 }), Some((3, 0.0)));
+        for (x, y) in [(-20, -20), (4, 1), (9, 9), (30, 4), (5, 7), (1, 6)] {
+            let point = Point { x, y };
+            let best = lines.iter().map(|line| line.distance_to(point)).fold(f64::MAX, f64::min);
+            let (idx, distance) = index.closest_line(point).unwrap();
+            assert_eq!(distance, best);
+            assert_eq!(lines[idx].distance_to(point), best);
+        }
+        // Best candidate of the first ring is farther than a line just past the bucket border
+        let index = LineIndex::new(vec!["0,0 -> 0,0".parse().unwrap(), "5,-3 -> 6,3".parse().unwrap()], 4);
+        let (idx, distance) = index.closest_line(Point { x: 3, y: 0 }).unwrap();
+        assert_eq!(idx, 1);
+        assert!(distance < 3.0);
+        // Far away queries start right at the extent
+        let far = Point { x: 1_000_000_000, y: -3 };
+        let index = LineIndex::new(test_lines(), 1);
+        let best = lines.iter().map(|line| line.distance_to(far)).fold(f64::MAX, f64::min);
+        assert_eq!(index.closest_line(far).map(|(_, distance)| distance), Some(best));
+        assert_eq!(index.ring((0, 0), 2, BoundingBox { min: Point { x: -1, y: -5 }, max: Point { x: 9, y: 9 } }).len(), 11);
+    }
+
+    #[test]
+    fn intersections() {
+        let index = LineIndex::new(test_lines(), 3);
+        let mut meeting = index.intersections(2);
+        meeting.sort_by_key(|(other, _)| *other);
+        assert_eq!(meeting, vec![
+            (1, Intersection::Crossing { x: 4.0, y: 4.0 }),
+            (4, Intersection::Crossing { x: 7.0, y: 4.0 }),
+            (5, Intersection::Crossing { x: 6.0, y: 4.0 }),
+            // Collinear lines sharing only an endpoint cross there
+            (7, Intersection::Crossing { x: 3.0, y: 4.0 }),
+            (8, Intersection::Crossing { x: 4.0, y: 4.0 }),
+            (9, Intersection::Crossing { x: 6.0, y: 4.0 }),
+        ]);
+    }
+}
@@ -0,0 +1,172 @@
+use std::fmt::Debug;
+use custom_error::custom_error;
+use crate::school::{School, Species};
+
+custom_error! {
+    #[derive(PartialEq)]
+    pub EcosystemError
+    UnknownPopulation{index: usize} = "No population with index {index}",
+}
+
+/// Species independent view of a school used by the ecosystem
+pub trait Population: Debug {
+    fn tick_with_births(&mut self, birth_rate: f64);
+    fn cull(&mut self, count: usize) -> usize;
+    fn count(&self) -> usize;
+}
+
+impl<S: Species> Population for School<S> {
+    fn tick_with_births(&mut self, birth_rate: f64) {
+        School::tick_with_births(self, birth_rate);
+    }
+    fn cull(&mut self, count: usize) -> usize {
+        School::cull(self, count)
+    }
+    fn count(&self) -> usize {
+        School::count(self)
+    }
+}
+
+/// Every day each predator eats `appetite` prey, predators that found no food starve when `starvation` is set
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Predation {
+    pub predator: usize,
+    pub prey: usize,
+    pub appetite: usize,
+    pub starvation: bool,
+}
+
+/// Several populations sharing food and space
+#[derive(Debug, Default)]
+pub struct Ecosystem {
+    populations: Vec<Box<dyn Population>>,
+    /// Logistic limit, births shrink as total population approaches it
+    carrying_capacity: Option<usize>,
+    predation: Vec<Predation>,
+}
+
+impl Ecosystem {
+    /// Adds population and returns its index used by predation rules
+    pub fn add(&mut self, population: Box<dyn Population>) -> usize {
+        self.populations.push(population);
+        self.populations.len() - 1
+    }
+    pub fn with_carrying_capacity(mut self, capacity: usize) -> Self {
+        self.carrying_capacity = Some(capacity);
+        self
+    }
+    /// Predator and prey have to be added before the rule
+    pub fn with_predation(mut self, predation: Predation) -> Result<Self, EcosystemError> {
+        if let Some(index) = [predation.predator, predation.prey].into_iter().find(|index| *index >= self.populations.len()) {
+            return Err(EcosystemError::UnknownPopulation { index });
+        }
+        self.predation.push(predation);
+        Ok(self)
+    }
+
+    pub fn tick(&mut self) -> &mut Self {
+        let birth_rate = match self.carrying_capacity {
+            Some(capacity) => 1.0 - self.count() as f64 / capacity.max(1) as f64,
+            None => 1.0,
+        };
+        self.populations.iter_mut()
+            .for_each(|population| population.tick_with_births(birth_rate));
+        for rule in &self.predation {
+            let predators = self.populations[rule.predator].count();
+            let eaten = self.populations[rule.prey].cull(predators * rule.appetite);
+            if rule.starvation && rule.appetite > 0 {
+                let fed = eaten.div_ceil(rule.appetite);
+                self.populations[rule.predator].cull(predators - fed);
+            }
+        }
+        self
+    }
+    pub fn count(&self) -> usize {
+        self.populations.iter().map(|population| population.count()).sum()
+    }
+    pub fn counts(&self) -> Vec<usize> {
+        self.populations.iter().map(|population| population.count()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ecosystem::{Ecosystem, EcosystemError, Predation};
+    use crate::LanternFishSchool;
+    use crate::model::PopulationModel;
+    use crate::school::{School, Species};
+
+    /// Splits in two every `period` days
+    #[derive(Debug, Clone, PartialEq)]
+    struct Amoeba {
+        period: u32,
+    }
+
+    impl Species for Amoeba {
+        type Individual = u32;
+
+        fn tick(&self, age: &mut u32) -> Option<Vec<u32>> {
+            *age += 1;
+            if *age == self.period {
+                *age = 0;
+                return Some(vec![0]);
+            }
+            Some(vec![])
+        }
+    }
+
+    #[test]
+    fn multiple_species() {
+        let mut ecosystem = Ecosystem::default();
+        ecosystem.add(Box::new("3,4,3,1,2".parse::<LanternFishSchool>().unwrap()));
+        ecosystem.add(Box::new(School::new(Amoeba { period: 3 }, vec![0])));
+        for _ in 0..18 {
+            ecosystem.tick();
+        }
+        assert_eq!(ecosystem.counts(), vec![26, 64]);
+        assert_eq!(ecosystem.count(), 90);
+    }
+
+    #[test]
+    fn carrying_capacity() {
+        let mut ecosystem = Ecosystem::default().with_carrying_capacity(1000);
+        ecosystem.add(Box::new("3,4,3,1,2".parse::<LanternFishSchool>().unwrap()));
+        ecosystem.add(Box::new("1,1,1".parse::<LanternFishSchool>().unwrap()
+            .with_species(PopulationModel::new(5, 1, 1, Some(40)).unwrap())));
+        let mut previous = ecosystem.count();
+        for _ in 0..400 {
+            ecosystem.tick();
+            assert!(ecosystem.count() <= 1000);
+            previous = previous.max(ecosystem.count());
+        }
+        assert!(previous > 900);
+    }
+
+    #[test]
+    fn single_fish_grows() {
+        let mut ecosystem = Ecosystem::default().with_carrying_capacity(1_000_000);
+        ecosystem.add(Box::new("0".parse::<LanternFishSchool>().unwrap()));
+        for _ in 0..80 {
+            ecosystem.tick();
+        }
+        assert!(ecosystem.count() > 100);
+    }
+
+    #[test]
+    fn predators() {
+        let mut ecosystem = Ecosystem::default();
+        let prey = ecosystem.add(Box::new(School::new(Amoeba { period: 1 }, vec![0; 5])));
+        let predator = ecosystem.add(Box::new(School::new(Amoeba { period: 100 }, vec![0; 4])));
+        let predation = Predation { predator, prey, appetite: 2, starvation: true };
+        assert_eq!(Ecosystem::default().with_predation(predation).unwrap_err(), EcosystemError::UnknownPopulation { index: 1 });
+        let mut ecosystem = ecosystem.with_predation(predation).unwrap();
+        ecosystem.tick();
+        // 10 amoebas, 8 eaten by 4 predators
+        assert_eq!(ecosystem.counts(), vec![2, 4]);
+        ecosystem.tick();
+        // 4 prey feed 2 predators, other 2 starve
+        assert_eq!(ecosystem.counts(), vec![0, 2]);
+        ecosystem.tick();
+        assert_eq!(ecosystem.counts(), vec![0, 0]);
+    }
+}
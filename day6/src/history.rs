@@ -0,0 +1,166 @@
+use std::io;
+use std::io::Write;
+use crate::model::{ModelError, PopulationModel};
+use crate::transition::TransitionMatrix;
+
+/// Population of every day split by timer, ages are summed up
+#[derive(Debug, Clone, PartialEq)]
+pub struct PopulationHistory {
+    /// `days[day][timer]`
+    days: Vec<Vec<u128>>,
+    totals: Vec<u128>,
+}
+
+impl PopulationHistory {
+    pub fn totals(&self) -> &[u128] {
+        &self.totals
+    }
+    pub fn by_timer(&self, day: usize) -> &[u128] {
+        &self.days[day]
+    }
+
+    /// One row per day: `day,total,timer_0,...,timer_n`
+    pub fn write_csv<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        let timers = self.days.first().map_or(0, |timers| timers.len());
+        let header: Vec<String> = (0..timers).map(|timer| format!("timer_{}", timer)).collect();
+        writeln!(writer, "day,total,{}", header.join(","))?;
+        for (day, (counts, total)) in self.days.iter().zip(&self.totals).enumerate() {
+            let counts: Vec<String> = counts.iter().map(|count| count.to_string()).collect();
+            writeln!(writer, "{},{},{}", day, total, counts.join(","))?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct GrowthAnalysis {
+    /// Asymptotic factor population grows by each day
+    pub growth_rate: f64,
+    /// Days after which population doubles, None when it does not grow
+    pub doubling_time: Option<f64>,
+}
+
+const POWER_ITERATIONS: usize = 100_000;
+const TOLERANCE: f64 = 1e-12;
+
+impl TransitionMatrix {
+    fn apply(&self, vector: &[f64]) -> Vec<f64> {
+        self.entries().iter()
+            .map(|row| row.iter().zip(vector).map(|(entry, value)| *entry as f64 * value).sum())
+            .collect()
+    }
+
+    /// Perron root found by power iteration on `M + I`, the shift keeps periodic models converging
+    pub fn dominant_eigenvalue(&self) -> f64 {
+        let size = self.states().len();
+        // Populations that die out have nilpotent transitions
+        let mut vector = vec![1.0; size];
+        for _ in 0..size {
+            vector = self.apply(&vector);
+        }
+        if vector.iter().all(|value| *value == 0.0) {
+            return 0.0;
+        }
+        let mut vector = vec![1.0 / size as f64; size];
+        let mut eigenvalue = 0.0;
+        for _ in 0..POWER_ITERATIONS {
+            let applied = self.apply(&vector);
+            eigenvalue = applied.iter().sum::<f64>();
+            let residual: f64 = applied.iter().zip(&vector)
+                .map(|(value, own)| (value - eigenvalue * own).abs())
+                .sum();
+            if residual < TOLERANCE {
+                break;
+            }
+            let shifted: Vec<f64> = applied.iter().zip(&vector).map(|(value, own)| value + own).collect();
+            let norm: f64 = shifted.iter().sum();
+            vector = shifted.into_iter().map(|value| value / norm).collect();
+        }
+        eigenvalue
+    }
+}
+
+impl PopulationModel {
+    /// Population of days `0..=days`, fails on the first day population does not fit in u128
+    pub fn history(&self, timers: &[i16], days: usize) -> Result<PopulationHistory, ModelError> {
+        if let Some(timer) = timers.iter().find(|timer| **timer < 0) {
+            return Err(ModelError::NegativeTimer { timer: *timer });
+        }
+        let max_timer = timers.iter().copied().max().unwrap_or(0).max(self.newborn_timer());
+        let mut buckets = PopulationModel::buckets(timers);
+        let mut history = vec![];
+        let mut totals = vec![];
+        for day in 0..=days {
+            if day > 0 {
+                buckets = self.checked_step(&buckets).ok_or(ModelError::Overflow { day })?;
+            }
+            let mut by_timer: Vec<u128> = vec![0; max_timer as usize + 1];
+            for ((timer, _), count) in &buckets {
+                let bucket = &mut by_timer[*timer as usize];
+                *bucket = bucket.checked_add(*count).ok_or(ModelError::Overflow { day })?;
+            }
+            let total = by_timer.iter().try_fold(0u128, |total, count| total.checked_add(*count))
+                .ok_or(ModelError::Overflow { day })?;
+            history.push(by_timer);
+            totals.push(total);
+        }
+        Ok(PopulationHistory { days: history, totals })
+    }
+
+    pub fn growth_analysis(&self) -> GrowthAnalysis {
+        let growth_rate = TransitionMatrix::new(self, 0).dominant_eigenvalue();
+        GrowthAnalysis {
+            growth_rate,
+            doubling_time: if growth_rate > 1.0 { Some(2f64.ln() / growth_rate.ln()) } else { None },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::model::{ModelError, PopulationModel};
+    use crate::parse_timers;
+
+    #[test]
+    fn history_csv() {
+        let history = PopulationModel::LANTERNFISH.history(&parse_timers("3,4,3,1,2").unwrap(), 18).unwrap();
+        let totals = history.totals();
+        assert_eq!(totals.len(), 19);
+        assert_eq!(totals[0], 5);
+        assert_eq!(totals[18], 26);
+        assert_eq!(history.by_timer(1), &[1, 1, 2, 1, 0, 0, 0, 0, 0]);
+        let mut csv = vec![];
+        history.write_csv(&mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        assert_eq!(csv.lines().next(), Some("day,total,timer_0,timer_1,timer_2,timer_3,timer_4,timer_5,timer_6,timer_7,timer_8"));
+        assert_eq!(csv.lines().nth(1), Some("0,5,0,1,1,2,1,0,0,0,0"));
+        assert_eq!(csv.lines().count(), 20);
+    }
+
+    #[test]
+    fn growth() {
+        let analysis = PopulationModel::LANTERNFISH.growth_analysis();
+        let rate = analysis.growth_rate;
+        // Characteristic polynomial of lanternfish transition is x^9 - x^2 - 1
+        assert!((rate.powi(9) - rate.powi(2) - 1.0).abs() < 1e-9);
+        assert!((analysis.doubling_time.unwrap() - 2f64.ln() / rate.ln()).abs() < 1e-9);
+        let history = PopulationModel::LANTERNFISH.history(&parse_timers("3,4,3,1,2").unwrap(), 900).unwrap();
+        let totals = history.totals();
+        assert!((totals[900] as f64 / totals[899] as f64 - rate).abs() < 1e-5);
+
+        let synchronized = PopulationModel::new(2, 0, 1, None).unwrap();
+        assert!((synchronized.growth_analysis().growth_rate - 2f64.sqrt()).abs() < 1e-9);
+        let dying = PopulationModel::new(7, 2, 1, Some(5)).unwrap();
+        assert_eq!(dying.growth_analysis(), crate::history::GrowthAnalysis { growth_rate: 0.0, doubling_time: None });
+    }
+
+    #[test]
+    fn invalid_history() {
+        let model = PopulationModel::LANTERNFISH;
+        assert_eq!(model.history(&[3, -1], 5), Err(ModelError::NegativeTimer { timer: -1 }));
+        match model.history(&parse_timers("3,4,3,1,2").unwrap(), 1100) {
+            Err(ModelError::Overflow { day }) => assert!(day > 900 && day <= 1100),
+            other => panic!("{:?}", other.map(|history| history.totals().len())),
+        }
+    }
+}
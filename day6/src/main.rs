@@ -1,20 +1,32 @@
-use std::collections::{HashMap, VecDeque};
 use std::fs;
+use std::fs::File;
 use std::str::FromStr;
+use crate::ecosystem::{Ecosystem, Predation};
+use crate::model::{ModelError, PopulationModel};
+use crate::school::{School, Species};
+
+mod model;
+mod transition;
+mod history;
+mod school;
+mod ecosystem;
 
 #[derive(Debug, Copy, Clone, PartialOrd, PartialEq)]
-struct LanternFish {
+pub struct LanternFish {
     timer: i16,
+    age: u32,
 }
 
 impl LanternFish {
-    pub fn tick(&mut self) -> Option<LanternFish> {
+    /// Advances fish by a day, returns newborns or None if fish died of age
+    pub fn tick(&mut self, model: &PopulationModel) -> Option<Vec<LanternFish>> {
+        self.age = model.next_age(self.age)?;
         self.timer -= 1;
         if self.timer < 0 {
-            self.timer = 6;
-            return Some(LanternFish { timer: 8 });
+            self.timer = model.reset_timer();
+            return Some(vec![LanternFish { timer: model.newborn_timer(), age: 0 }; model.litter_size() as usize]);
         }
-        None
+        Some(vec![])
     }
 }
 
@@ -23,72 +35,56 @@ impl FromStr for LanternFish {
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s.parse() {
-            Ok(timer) => Ok(LanternFish { timer: timer }),
+            Ok(timer) => Ok(LanternFish { timer, age: 0 }),
             Err(_) => Err(())
         }
     }
 }
 
-#[derive(PartialOrd, PartialEq, Debug, Clone)]
-struct LanternFishSchool(Vec<LanternFish>);
-
-impl LanternFishSchool {
-    pub fn tick(&mut self) -> &mut Self {
-        let new_fishes: Vec<LanternFish> = self.0.iter_mut()
-            .filter_map(|fish| fish.tick())
-            .collect();
-        self.0.extend(new_fishes);
-        // println!("Fishes: {:?}", self.0);
-        self
-    }
-    pub fn count(&self) -> usize {
-        self.0.len()
+impl Species for PopulationModel {
+    type Individual = LanternFish;
+
+    fn tick(&self, fish: &mut LanternFish) -> Option<Vec<LanternFish>> {
+        fish.tick(self)
     }
 }
 
+type LanternFishSchool = School<PopulationModel>;
+
 impl FromStr for LanternFishSchool {
     type Err = ();
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        Ok(LanternFishSchool(
-            s.split(',').into_iter()
-                .filter_map(|fish_str| fish_str.parse().ok())
-                .collect()
+        Ok(School::new(
+            PopulationModel::default(),
+            s.split(',')
+                .filter_map(|fish_str| fish_str.trim().parse().ok())
+                .collect(),
         ))
     }
 }
 
-fn fish_at_day(string: &str, days: usize) -> usize {
-    let mut immature_fish = VecDeque::from([0, 0]);
-    let mut new_fish_at_day: HashMap<usize, u128> = HashMap::from(
-        [(0, 0), (1, 0), (2, 0), (3, 0), (4, 0), (5, 0), (6, 0)]);
+/// Comma separated timers, empty entries are skipped
+fn parse_timers(string: &str) -> Result<Vec<i16>, ModelError> {
     string.split(',')
-        .into_iter()
-        .filter_map(|s| s.parse().ok())
-        .for_each(|int: usize| {
-            let fish = new_fish_at_day.entry(int).or_insert(0);
-            *fish += 1;
-        });
-    let mut day = 0;
-    for _ in 0..=days {
-        day += 1;
-        if day == 7 { day = 0; }
-        let fishes_to_spawn = *new_fish_at_day.get(&day).unwrap();
-
-        immature_fish.push_back(fishes_to_spawn);
-        if let Some(fishes_at_today) = new_fish_at_day.get_mut(&day) {
-            *fishes_at_today = *fishes_at_today + immature_fish.pop_front().unwrap();
-        }
-        assert_eq!(immature_fish.len(), 2);
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(|s| match s.parse::<i16>() {
+            Ok(timer) if timer < 0 => Err(ModelError::NegativeTimer { timer }),
+            Ok(timer) => Ok(timer),
+            Err(_) => Err(ModelError::InvalidTimer { timer: s.to_string() }),
+        })
+        .collect()
+}
 
-        println!("{:#?}", new_fish_at_day);
-    }
-    new_fish_at_day.iter().fold(0, |acc, (_, count)| acc + *count as usize)
+fn fish_at_day(string: &str, days: usize) -> u128 {
+    PopulationModel::LANTERNFISH.count_at_day(&parse_timers(string).unwrap(), days)
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{fish_at_day, LanternFishSchool};
+    use crate::{fish_at_day, LanternFishSchool, parse_timers};
+    use crate::model::{ModelError, PopulationModel};
 
     #[test]
     fn with_test_data() {
@@ -110,7 +106,7 @@ mod tests {
             let mut fishes: LanternFishSchool = "3,4,3,1,2".parse().unwrap();
             for _ in 0..i { fishes.tick(); }
             println!("{:?}", fishes);
-            assert_eq!(fish_at_day("3,4,3,1,2", i), fishes.count());
+            assert_eq!(fish_at_day("3,4,3,1,2", i), fishes.count() as u128);
         }
         let mut fishes: LanternFishSchool = "3,4,3,1,2".parse().unwrap();
         for _ in 1..=18 { fishes.tick(); }
@@ -120,14 +116,73 @@ mod tests {
         assert_eq!(fishes.count(), 5934);
         assert_eq!(fish_at_day("3,4,3,1,2", 80), 5934);
     }
+
+    #[test]
+    fn simulation_agrees_with_buckets() {
+        let models = [
+            PopulationModel::LANTERNFISH,
+            PopulationModel::new(3, 1, 2, None).unwrap(),
+            PopulationModel::new(7, 2, 1, Some(20)).unwrap(),
+            PopulationModel::new(4, 0, 3, Some(9)).unwrap(),
+        ];
+        for model in models {
+            let mut fishes = "3,4,3,1,2".parse::<LanternFishSchool>().unwrap().with_species(model);
+            for day in 0..=40 {
+                assert_eq!(model.count_at_day(&parse_timers("3,4,3,1,2").unwrap(), day), fishes.count() as u128, "{:?} day {}", model, day);
+                fishes.tick();
+            }
+        }
+        let mut mortal = "0".parse::<LanternFishSchool>().unwrap()
+            .with_species(PopulationModel::new(7, 2, 1, Some(3)).unwrap());
+        assert_eq!(mortal.tick().count(), 2);
+        mortal.tick();
+        assert_eq!(mortal.tick().count(), 1);
+    }
+
+    #[test]
+    fn invalid_model() {
+        assert_eq!(PopulationModel::new(0, 2, 1, None), Err(ModelError::InvalidCycleLength { cycle_length: 0 }));
+        assert_eq!(PopulationModel::new(1, 0, 1, None).map(|model| model.reset_timer()), Ok(0));
+    }
+
+    #[test]
+    fn timers() {
+        assert_eq!(parse_timers("3,4,\n"), Ok(vec![3, 4]));
+        assert_eq!(parse_timers("3,-1"), Err(ModelError::NegativeTimer { timer: -1 }));
+        assert_eq!(parse_timers("3,x"), Err(ModelError::InvalidTimer { timer: "x".to_string() }));
+    }
 }
 
 fn main() {
     let str = fs::read_to_string("day6/input_data.dat").unwrap();
     let mut fishes: LanternFishSchool = str.parse().unwrap();
-    for i in 1..=80 {
+    for _ in 1..=80 {
         fishes.tick();
-        println!("Day {}: {} fishes", i, fishes.count())
     }
+    println!("Day 80: {} fishes", fishes.count());
     println!("Day 256: {} fishes", fish_at_day(str.as_str(), 256));
+    let timers = parse_timers(str.as_str()).unwrap();
+    if let Ok(file) = File::create("day6/history.csv") {
+        PopulationModel::LANTERNFISH.history(&timers, 256).unwrap().write_csv(file).unwrap();
+    }
+    let analysis = PopulationModel::LANTERNFISH.growth_analysis();
+    println!("Growth rate: {:.6} per day, doubling every {:.3} days",
+             analysis.growth_rate, analysis.doubling_time.unwrap());
+    println!("Day 10000: {} fishes", PopulationModel::LANTERNFISH.count_at_day_exact(&timers, 10_000).unwrap());
+    println!("Day 10^18: {} fishes modulo 1000000007",
+             PopulationModel::LANTERNFISH.count_at_day_modulo(&timers, 1_000_000_000_000_000_000, 1_000_000_007).unwrap());
+
+    // Lanternfish hunted by slower breeding mortal predators in a sea that fits a million fish
+    let mut ecosystem = Ecosystem::default().with_carrying_capacity(1_000_000);
+    let prey = ecosystem.add(Box::new(str.parse::<LanternFishSchool>().unwrap()));
+    let predators = "8,8,8,8,8".parse::<LanternFishSchool>().unwrap()
+        .with_species(PopulationModel::new(12, 4, 1, Some(60)).unwrap());
+    let predator = ecosystem.add(Box::new(predators));
+    let mut ecosystem = ecosystem
+        .with_predation(Predation { predator, prey, appetite: 1, starvation: true })
+        .unwrap();
+    for _ in 1..=150 {
+        ecosystem.tick();
+    }
+    println!("Day 150 with predators: {:?} fishes", ecosystem.counts());
 }
@@ -0,0 +1,113 @@
+use std::collections::BTreeMap;
+use custom_error::custom_error;
+
+custom_error! {
+    #[derive(PartialEq)]
+    pub ModelError
+    InvalidCycleLength{cycle_length: u8} = "Cycle length has to be at least 1, got {cycle_length}",
+    ZeroModulus = "Modulus has to be positive",
+    NegativeTimer{timer: i16} = "Timer {timer} is negative",
+    InvalidTimer{timer: String} = "Timer {timer} is not a number",
+    Overflow{day: usize} = "Population does not fit in u128 on day {day}",
+}
+
+/// Reproduction parameters shared by per-fish simulation and bucketed counting
+#[derive(Debug, Copy, Clone, PartialOrd, PartialEq)]
+pub struct PopulationModel {
+    /// Days between two litters of a mature fish
+    cycle_length: u8,
+    /// Extra days a newborn needs before its first cycle
+    maturation_delay: u8,
+    /// Fish born at once
+    litter_size: u32,
+    /// Days after which a fish dies, ages are only tracked when set
+    max_lifespan: Option<u32>,
+}
+
+impl PopulationModel {
+    pub const LANTERNFISH: PopulationModel = PopulationModel {
+        cycle_length: 7,
+        maturation_delay: 2,
+        litter_size: 1,
+        max_lifespan: None,
+    };
+
+    /// Fish with a cycle shorter than a day would reset their timer below zero
+    pub fn new(cycle_length: u8, maturation_delay: u8, litter_size: u32, max_lifespan: Option<u32>) -> Result<Self, ModelError> {
+        if cycle_length < 1 {
+            return Err(ModelError::InvalidCycleLength { cycle_length });
+        }
+        Ok(PopulationModel { cycle_length, maturation_delay, litter_size, max_lifespan })
+    }
+    pub fn litter_size(&self) -> u32 {
+        self.litter_size
+    }
+    pub fn max_lifespan(&self) -> Option<u32> {
+        self.max_lifespan
+    }
+
+    pub fn reset_timer(&self) -> i16 {
+        self.cycle_length as i16 - 1
+    }
+    pub fn newborn_timer(&self) -> i16 {
+        self.reset_timer() + self.maturation_delay as i16
+    }
+    /// Age after a day passes, None when fish dies
+    pub fn next_age(&self, age: u32) -> Option<u32> {
+        match self.max_lifespan {
+            None => Some(age),
+            Some(lifespan) if age + 1 >= lifespan => None,
+            Some(_) => Some(age + 1),
+        }
+    }
+
+    /// States fish in given state turns into after a day together with fish count for each
+    pub fn successors(&self, timer: i16, age: u32) -> Vec<((i16, u32), u32)> {
+        match self.next_age(age) {
+            None => vec![],
+            Some(age) if timer == 0 => vec![
+                ((self.reset_timer(), age), 1),
+                ((self.newborn_timer(), 0), self.litter_size),
+            ],
+            Some(age) => vec![((timer - 1, age), 1)],
+        }
+    }
+
+    /// Buckets of fish with equal timer and age after a day
+    pub fn step(&self, buckets: &BTreeMap<(i16, u32), u128>) -> BTreeMap<(i16, u32), u128> {
+        self.checked_step(buckets).expect("Population does not fit in u128")
+    }
+    /// Same as `step`, None when a bucket overflows
+    pub fn checked_step(&self, buckets: &BTreeMap<(i16, u32), u128>) -> Option<BTreeMap<(i16, u32), u128>> {
+        let mut next: BTreeMap<(i16, u32), u128> = BTreeMap::new();
+        for (&(timer, age), count) in buckets {
+            for (state, multiplier) in self.successors(timer, age) {
+                let bucket = next.entry(state).or_insert(0);
+                *bucket = bucket.checked_add(count.checked_mul(multiplier as u128)?)?;
+            }
+        }
+        Some(next)
+    }
+
+    /// Initial buckets, every fish is a newborn
+    pub fn buckets(timers: &[i16]) -> BTreeMap<(i16, u32), u128> {
+        let mut buckets: BTreeMap<(i16, u32), u128> = BTreeMap::new();
+        timers.iter().for_each(|timer| *buckets.entry((*timer, 0)).or_insert(0) += 1);
+        buckets
+    }
+
+    /// Population after `days` counted in buckets of fish with equal timer and age
+    pub fn count_at_day(&self, timers: &[i16], days: usize) -> u128 {
+        let mut buckets = Self::buckets(timers);
+        for _ in 0..days {
+            buckets = self.step(&buckets);
+        }
+        buckets.values().sum()
+    }
+}
+
+impl Default for PopulationModel {
+    fn default() -> Self {
+        PopulationModel::LANTERNFISH
+    }
+}
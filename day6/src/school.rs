@@ -0,0 +1,62 @@
+use std::fmt::Debug;
+
+/// Definition of how individuals of a species live, reproduce and die
+pub trait Species: Debug + Clone + PartialEq {
+    type Individual: Debug + Clone + PartialEq;
+
+    /// Advances individual by a day, returns newborns or None if it died
+    fn tick(&self, individual: &mut Self::Individual) -> Option<Vec<Self::Individual>>;
+}
+
+/// Population of a single species
+#[derive(PartialEq, Debug, Clone)]
+pub struct School<S: Species> {
+    species: S,
+    members: Vec<S::Individual>,
+    /// Fraction of a newborn carried over to the next births
+    birth_remainder: f64,
+}
+
+impl<S: Species> School<S> {
+    pub fn new(species: S, members: Vec<S::Individual>) -> Self {
+        School { species, members, birth_remainder: 0.0 }
+    }
+    pub fn with_species(mut self, species: S) -> Self {
+        self.species = species;
+        self
+    }
+    pub fn tick(&mut self) -> &mut Self {
+        self.tick_with_births(1.0)
+    }
+    /// Advances every member by a day, only `birth_rate` part of newborns survives.
+    ///
+    /// Fractions of newborns add up over days, so small schools still grow at rates below 1
+    pub fn tick_with_births(&mut self, birth_rate: f64) -> &mut Self {
+        let species = &self.species;
+        let mut newborns: Vec<S::Individual> = vec![];
+        self.members.retain_mut(|member| match species.tick(member) {
+            Some(born) => {
+                newborns.extend(born);
+                true
+            }
+            None => false
+        });
+        if !newborns.is_empty() {
+            let expected = newborns.len() as f64 * birth_rate.clamp(0.0, 1.0) + self.birth_remainder;
+            let surviving = (expected.floor() as usize).min(newborns.len());
+            self.birth_remainder = expected - surviving as f64;
+            newborns.truncate(surviving);
+        }
+        self.members.extend(newborns);
+        self
+    }
+    /// Removes up to `count` youngest members, returns how many were removed
+    pub fn cull(&mut self, count: usize) -> usize {
+        let removed = count.min(self.members.len());
+        self.members.truncate(self.members.len() - removed);
+        removed
+    }
+    pub fn count(&self) -> usize {
+        self.members.len()
+    }
+}
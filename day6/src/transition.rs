@@ -0,0 +1,193 @@
+use num_bigint::BigUint;
+use crate::model::{ModelError, PopulationModel};
+
+/// Number type populations are computed in
+pub trait Arithmetic {
+    type Value: Clone;
+    fn value_of(&self, value: u32) -> Self::Value;
+    fn add(&self, lhs: &Self::Value, rhs: &Self::Value) -> Self::Value;
+    fn mul(&self, lhs: &Self::Value, rhs: &Self::Value) -> Self::Value;
+}
+
+/// Arbitrary precision, size of the result grows linearly with days
+pub struct Exact;
+
+impl Arithmetic for Exact {
+    type Value = BigUint;
+
+    fn value_of(&self, value: u32) -> BigUint {
+        BigUint::from(value)
+    }
+    fn add(&self, lhs: &BigUint, rhs: &BigUint) -> BigUint {
+        lhs + rhs
+    }
+    fn mul(&self, lhs: &BigUint, rhs: &BigUint) -> BigUint {
+        lhs * rhs
+    }
+}
+
+/// Results modulo given number, usually a prime
+pub struct Modulo(u64);
+
+impl Modulo {
+    pub fn new(modulus: u64) -> Result<Self, ModelError> {
+        match modulus {
+            0 => Err(ModelError::ZeroModulus),
+            _ => Ok(Modulo(modulus)),
+        }
+    }
+}
+
+impl Arithmetic for Modulo {
+    type Value = u64;
+
+    fn value_of(&self, value: u32) -> u64 {
+        value as u64 % self.0
+    }
+    fn add(&self, lhs: &u64, rhs: &u64) -> u64 {
+        ((*lhs as u128 + *rhs as u128) % self.0 as u128) as u64
+    }
+    fn mul(&self, lhs: &u64, rhs: &u64) -> u64 {
+        ((*lhs as u128 * *rhs as u128) % self.0 as u128) as u64
+    }
+}
+
+/// One day of population change, `entries[to][from]` fish in state `to` come from one in `from`
+#[derive(Debug, Clone, PartialEq)]
+pub struct TransitionMatrix {
+    states: Vec<(i16, u32)>,
+    entries: Vec<Vec<u32>>,
+}
+
+impl TransitionMatrix {
+    /// States cover timers up to `max_timer` so that initial fish with long timers fit
+    pub fn new(model: &PopulationModel, max_timer: i16) -> Self {
+        let max_timer = max_timer.max(model.newborn_timer());
+        let ages = model.max_lifespan().unwrap_or(1).max(1);
+        let states: Vec<(i16, u32)> = (0..ages)
+            .flat_map(|age| (0..=max_timer).map(move |timer| (timer, age)))
+            .collect();
+        let mut entries = vec![vec![0; states.len()]; states.len()];
+        states.iter().enumerate().for_each(|(from, (timer, age))| {
+            model.successors(*timer, *age).into_iter().for_each(|(state, count)| {
+                let to = states.iter().position(|candidate| *candidate == state).unwrap();
+                entries[to][from] += count;
+            })
+        });
+        TransitionMatrix { states, entries }
+    }
+
+    pub fn states(&self) -> &[(i16, u32)] {
+        &self.states
+    }
+    pub fn entries(&self) -> &[Vec<u32>] {
+        &self.entries
+    }
+
+    /// Population in every state after `days`, starting with newborn fish with given timers
+    pub fn distribution_after<A: Arithmetic>(&self, arithmetic: &A, timers: &[i16], mut days: u64) -> Result<Vec<A::Value>, ModelError> {
+        if let Some(timer) = timers.iter().find(|timer| **timer < 0) {
+            return Err(ModelError::NegativeTimer { timer: *timer });
+        }
+        let mut vector: Vec<A::Value> = self.states.iter()
+            .map(|state| arithmetic.value_of(timers.iter().filter(|timer| (**timer, 0) == *state).count() as u32))
+            .collect();
+        let mut power: Vec<Vec<A::Value>> = self.entries.iter()
+            .map(|row| row.iter().map(|entry| arithmetic.value_of(*entry)).collect())
+            .collect();
+        while days > 0 {
+            if days & 1 == 1 {
+                vector = multiply_vector(arithmetic, &power, &vector);
+            }
+            days >>= 1;
+            if days > 0 {
+                power = multiply(arithmetic, &power, &power);
+            }
+        }
+        Ok(vector)
+    }
+
+    pub fn population_after<A: Arithmetic>(&self, arithmetic: &A, timers: &[i16], days: u64) -> Result<A::Value, ModelError> {
+        Ok(self.distribution_after(arithmetic, timers, days)?.iter()
+            .fold(arithmetic.value_of(0), |acc, count| arithmetic.add(&acc, count)))
+    }
+}
+
+fn dot<A: Arithmetic>(arithmetic: &A, lhs: impl Iterator<Item=A::Value>, rhs: impl Iterator<Item=A::Value>) -> A::Value {
+    lhs.zip(rhs).fold(arithmetic.value_of(0), |acc, (l, r)| arithmetic.add(&acc, &arithmetic.mul(&l, &r)))
+}
+
+fn multiply_vector<A: Arithmetic>(arithmetic: &A, matrix: &[Vec<A::Value>], vector: &[A::Value]) -> Vec<A::Value> {
+    matrix.iter()
+        .map(|row| dot(arithmetic, row.iter().cloned(), vector.iter().cloned()))
+        .collect()
+}
+
+fn multiply<A: Arithmetic>(arithmetic: &A, lhs: &[Vec<A::Value>], rhs: &[Vec<A::Value>]) -> Vec<Vec<A::Value>> {
+    lhs.iter()
+        .map(|row| (0..rhs.len())
+            .map(|col| dot(arithmetic, row.iter().cloned(), rhs.iter().map(|rhs_row| rhs_row[col].clone())))
+            .collect())
+        .collect()
+}
+
+impl PopulationModel {
+    /// Exact population after `days` in O(log days) matrix multiplications
+    pub fn count_at_day_exact(&self, timers: &[i16], days: u64) -> Result<BigUint, ModelError> {
+        let max_timer = timers.iter().copied().max().unwrap_or(0);
+        TransitionMatrix::new(self, max_timer).population_after(&Exact, timers, days)
+    }
+    /// Population after `days` modulo `modulus`, usable for any day count that fits in u64
+    pub fn count_at_day_modulo(&self, timers: &[i16], days: u64, modulus: u64) -> Result<u64, ModelError> {
+        let max_timer = timers.iter().copied().max().unwrap_or(0);
+        TransitionMatrix::new(self, max_timer).population_after(&Modulo::new(modulus)?, timers, days)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use num_bigint::BigUint;
+    use crate::model::{ModelError, PopulationModel};
+    use crate::parse_timers;
+
+    #[test]
+    fn agrees_with_buckets() {
+        let timers = parse_timers("3,4,3,1,2").unwrap();
+        let models = [
+            PopulationModel::LANTERNFISH,
+            PopulationModel::new(3, 1, 2, None).unwrap(),
+            PopulationModel::new(4, 0, 3, Some(9)).unwrap(),
+        ];
+        for model in models {
+            for day in (0..=60).step_by(6) {
+                let expected = model.count_at_day(&timers, day);
+                assert_eq!(model.count_at_day_exact(&timers, day as u64), Ok(BigUint::from(expected)));
+                assert_eq!(model.count_at_day_modulo(&timers, day as u64, 1_000_000_007), Ok((expected % 1_000_000_007) as u64));
+            }
+        }
+        assert_eq!(PopulationModel::LANTERNFISH.count_at_day_exact(&timers, 256), Ok(BigUint::from(26984457539u64)));
+        assert_eq!(PopulationModel::LANTERNFISH.count_at_day_exact(&parse_timers("12").unwrap(), 13),
+                   Ok(BigUint::from(PopulationModel::LANTERNFISH.count_at_day(&[12], 13))));
+    }
+
+    #[test]
+    fn astronomical_days() {
+        let timers = parse_timers("3,4,3,1,2").unwrap();
+        let model = PopulationModel::LANTERNFISH;
+        let prime = 998_244_353;
+        let value = model.count_at_day_modulo(&timers, 1_000_000_000_000_000_000, prime).unwrap();
+        assert!(value < prime);
+        // Population on day n + 9 equals populations of days n + 2 and n summed
+        let n = 999_999_999_999_999_000;
+        let sum = (model.count_at_day_modulo(&timers, n + 2, prime).unwrap() + model.count_at_day_modulo(&timers, n, prime).unwrap()) % prime;
+        assert_eq!(model.count_at_day_modulo(&timers, n + 9, prime), Ok(sum));
+    }
+
+    #[test]
+    fn invalid_input() {
+        let model = PopulationModel::LANTERNFISH;
+        assert_eq!(model.count_at_day_modulo(&[3, 4], 10, 0), Err(ModelError::ZeroModulus));
+        assert_eq!(model.count_at_day_exact(&[3, -1, 2], 10), Err(ModelError::NegativeTimer { timer: -1 }));
+        assert_eq!(model.count_at_day_modulo(&[-2], 10, 7), Err(ModelError::NegativeTimer { timer: -2 }));
+    }
+}
@@ -0,0 +1,145 @@
+use std::ops::Div;
+
+/// What optimizer may assume about a cost model
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Shape {
+    Linear,
+    Triangular,
+    Quadratic,
+    /// Total fuel is convex in target position
+    Convex,
+    Unknown,
+}
+
+/// Fuel a crab needs to move by given distance
+pub trait CostModel {
+    fn cost(&self, distance: i64) -> i64;
+    /// Factor fuel of crab with index `crab` is scaled by
+    fn weight(&self, _crab: usize) -> i64 {
+        1
+    }
+    /// Fuel used by crab with index `crab`
+    fn crab_cost(&self, crab: usize, distance: i64) -> i64 {
+        self.weight(crab) * self.cost(distance)
+    }
+    fn shape(&self) -> Shape {
+        Shape::Unknown
+    }
+    /// Shape of `cost` alone, weights aside
+    fn cost_shape(&self) -> Shape {
+        self.shape()
+    }
+}
+
+/// One unit of fuel per step
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Linear;
+
+/// Every step costs one more than the previous one
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Triangular;
+
+/// Square of distance
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Quadratic;
+
+/// Marks a user defined cost as convex in distance so that it can be optimized without brute force
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Convex<F: Fn(i64) -> i64>(pub F);
+
+/// Scales cost of every crab by its weight, crabs without weight use 1
+#[derive(Debug, Clone, PartialEq)]
+pub struct Weighted<C: CostModel> {
+    pub weights: Vec<i64>,
+    pub model: C,
+}
+
+impl CostModel for Linear {
+    fn cost(&self, distance: i64) -> i64 {
+        distance.abs()
+    }
+    fn shape(&self) -> Shape {
+        Shape::Linear
+    }
+}
+
+impl CostModel for Triangular {
+    fn cost(&self, distance: i64) -> i64 {
+        let distance = distance.abs();
+        (distance * (distance + 1)).div(2)
+    }
+    fn shape(&self) -> Shape {
+        Shape::Triangular
+    }
+}
+
+impl CostModel for Quadratic {
+    fn cost(&self, distance: i64) -> i64 {
+        distance * distance
+    }
+    fn shape(&self) -> Shape {
+        Shape::Quadratic
+    }
+}
+
+impl<F: Fn(i64) -> i64> CostModel for Convex<F> {
+    fn cost(&self, distance: i64) -> i64 {
+        self.0(distance)
+    }
+    fn shape(&self) -> Shape {
+        Shape::Convex
+    }
+}
+
+impl<C: CostModel> CostModel for Weighted<C> {
+    fn cost(&self, distance: i64) -> i64 {
+        self.model.cost(distance)
+    }
+    fn weight(&self, crab: usize) -> i64 {
+        self.weights.get(crab).copied().unwrap_or(1) * self.model.weight(crab)
+    }
+    /// Sum of convex costs with non negative weights stays convex
+    fn shape(&self) -> Shape {
+        match self.model.shape() {
+            Shape::Unknown => Shape::Unknown,
+            _ if self.weights.iter().any(|weight| *weight < 0) => Shape::Unknown,
+            _ => Shape::Convex,
+        }
+    }
+    fn cost_shape(&self) -> Shape {
+        self.model.cost_shape()
+    }
+}
+
+impl<F: Fn(i64) -> i64> CostModel for F {
+    fn cost(&self, distance: i64) -> i64 {
+        self(distance)
+    }
+}
+
+/// Fuel used by all crabs to move to `target`
+pub fn total_fuel<C: CostModel + ?Sized>(positions: &[i64], target: i64, model: &C) -> i128 {
+    positions.iter().enumerate()
+        .map(|(crab, pos)| model.crab_cost(crab, pos - target) as i128)
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cost::{CostModel, Linear, Quadratic, total_fuel, Triangular, Weighted};
+
+    #[test]
+    fn models() {
+        assert_eq!(Linear.cost(-5), 5);
+        assert_eq!(Triangular.cost(-4), 10);
+        assert_eq!(Quadratic.cost(-3), 9);
+        let weighted = Weighted { weights: vec![3, 0], model: Linear };
+        assert_eq!(weighted.crab_cost(0, 2), 6);
+        assert_eq!(weighted.crab_cost(1, 2), 0);
+        assert_eq!(weighted.crab_cost(2, 2), 2);
+        let cubic = |distance: i64| distance.abs().pow(3);
+        assert_eq!(cubic.cost(-2), 8);
+        assert_eq!(total_fuel(&[16, 1, 2, 0, 4, 2, 7, 1, 2, 14], 2, &Linear), 37);
+        assert_eq!(total_fuel(&[16, 1, 2, 0, 4, 2, 7, 1, 2, 14], 5, &Triangular), 168);
+    }
+}
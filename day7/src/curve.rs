@@ -0,0 +1,92 @@
+use std::io;
+use std::io::Write;
+use std::ops::RangeInclusive;
+use crate::cost::CostModel;
+use crate::FuelError;
+use crate::optimizer::fuel_evaluator;
+
+/// Total fuel for every target position in a range
+#[derive(Debug, Clone, PartialEq)]
+pub struct FuelCurve {
+    start: i64,
+    fuel: Vec<i128>,
+}
+
+impl FuelCurve {
+    pub fn new<C: CostModel + ?Sized>(positions: &[i64], range: RangeInclusive<i64>, model: &C) -> Result<Self, FuelError> {
+        if positions.is_empty() {
+            return Err(FuelError::EmptyInput);
+        }
+        if range.is_empty() {
+            return Err(FuelError::EmptyRange);
+        }
+        let fuel_at = fuel_evaluator(positions, model);
+        Ok(FuelCurve { start: *range.start(), fuel: range.map(fuel_at).collect() })
+    }
+
+    /// Curve between leftmost and rightmost crab
+    pub fn over_crabs<C: CostModel + ?Sized>(positions: &[i64], model: &C) -> Result<Self, FuelError> {
+        let (min, max) = (positions.iter().min().ok_or(FuelError::EmptyInput)?, positions.iter().max().unwrap());
+        FuelCurve::new(positions, *min..=*max, model)
+    }
+
+    /// `(position, fuel)` pairs in increasing position
+    pub fn points(&self) -> impl Iterator<Item=(i64, i128)> + '_ {
+        self.fuel.iter().enumerate().map(|(idx, fuel)| (self.start + idx as i64, *fuel))
+    }
+
+    pub fn minimum(&self) -> i128 {
+        *self.fuel.iter().min().unwrap()
+    }
+
+    /// Positions that need at most `slack` more fuel than the optimum, shows how flat it is
+    pub fn within(&self, slack: i128) -> Vec<i64> {
+        let minimum = self.minimum();
+        self.points()
+            .filter(|(_, fuel)| *fuel <= minimum + slack)
+            .map(|(position, _)| position)
+            .collect()
+    }
+
+    pub fn optimal_positions(&self) -> Vec<i64> {
+        self.within(0)
+    }
+
+    /// One row per position: `position,fuel`
+    pub fn write_csv<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writeln!(writer, "position,fuel")?;
+        for (position, fuel) in self.points() {
+            writeln!(writer, "{},{}", position, fuel)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cost::{Linear, Triangular};
+    use crate::curve::FuelCurve;
+    use crate::FuelError;
+
+    #[test]
+    fn curve() {
+        let crabs = [16, 1, 2, 0, 4, 2, 7, 1, 2, 14];
+        let curve = FuelCurve::over_crabs(&crabs, &Triangular).unwrap();
+        assert_eq!(curve.points().count(), 17);
+        assert_eq!(curve.minimum(), 168);
+        assert_eq!(curve.optimal_positions(), vec![5]);
+        assert_eq!(curve.points().nth(2), Some((2, 206)));
+        assert_eq!(FuelCurve::over_crabs(&[1, 3, 6, 8], &Linear).unwrap().optimal_positions(), vec![3, 4, 5, 6]);
+        // Curve may reach outside the crabs and be monotonic
+        let left = FuelCurve::new(&crabs, -5..=1, &Linear).unwrap();
+        assert_eq!(left.optimal_positions(), vec![1]);
+        assert_eq!(left.within(10), vec![0, 1]);
+        assert_eq!(FuelCurve::new(&[], 0..=3, &Linear), Err(FuelError::EmptyInput));
+        #[allow(clippy::reversed_empty_ranges)]
+        let empty = FuelCurve::new(&crabs, 3..=0, &Linear);
+        assert_eq!(empty, Err(FuelError::EmptyRange));
+        let mut csv = vec![];
+        FuelCurve::new(&crabs, 1..=2, &Linear).unwrap().write_csv(&mut csv).unwrap();
+        assert_eq!(String::from_utf8(csv).unwrap(), "position,fuel\n1,41\n2,37\n");
+    }
+}
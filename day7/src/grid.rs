@@ -0,0 +1,172 @@
+use std::str::FromStr;
+use crate::cost::{CostModel, total_fuel};
+use crate::optimizer::align;
+
+/// Crab on a grid of any dimension
+#[derive(Debug, Clone, PartialEq)]
+pub struct Position(pub Vec<i64>);
+
+impl FromStr for Position {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.trim().trim_start_matches('(').trim_end_matches(')')
+            .split(',')
+            .map(|coordinate| coordinate.trim().parse().map_err(|_| ()))
+            .collect::<Result<Vec<i64>, ()>>()
+            .map(Position)
+    }
+}
+
+/// One `x,y[,z...]` tuple per line, every crab has to have the same dimension
+pub fn parse_crabs(string: &str) -> Result<Vec<Position>, ()> {
+    let crabs = string.lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.parse())
+        .collect::<Result<Vec<Position>, ()>>()?;
+    match crabs.first() {
+        Some(first) if crabs.iter().any(|crab| crab.0.len() != first.0.len()) => Err(()),
+        _ => Ok(crabs),
+    }
+}
+
+fn axis(crabs: &[Position], axis: usize) -> Vec<i64> {
+    crabs.iter().map(|crab| crab.0[axis]).collect()
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GridAlignment {
+    pub position: Position,
+    pub fuel: i128,
+}
+
+/// Fuel when every axis is paid separately with the same cost model, `Linear` gives Manhattan distance
+pub fn separable_fuel<C: CostModel + ?Sized>(crabs: &[Position], target: &Position, model: &C) -> i128 {
+    target.0.iter().enumerate()
+        .map(|(idx, coordinate)| total_fuel(&axis(crabs, idx), *coordinate, model))
+        .sum()
+}
+
+/// Separable costs are optimized on every axis on its own with the 1D optimizer
+pub fn align_separable<C: CostModel + ?Sized>(crabs: &[Position], model: &C) -> Option<GridAlignment> {
+    let dimension = crabs.first()?.0.len();
+    let axes = (0..dimension)
+        .map(|idx| align(&axis(crabs, idx), model))
+        .collect::<Option<Vec<_>>>()?;
+    Some(GridAlignment {
+        position: Position(axes.iter().map(|alignment| alignment.position).collect()),
+        fuel: axes.iter().map(|alignment| alignment.fuel).sum(),
+    })
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EuclideanAlignment {
+    pub position: Vec<f64>,
+    pub fuel: f64,
+}
+
+const WEISZFELD_ITERATIONS: usize = 10_000;
+const WEISZFELD_TOLERANCE: f64 = 1e-9;
+
+fn distance(crab: &Position, point: &[f64]) -> f64 {
+    crab.0.iter().zip(point).map(|(c, p)| (*c as f64 - p).powi(2)).sum::<f64>().sqrt()
+}
+
+pub fn euclidean_fuel(crabs: &[Position], point: &[f64]) -> f64 {
+    crabs.iter().map(|crab| distance(crab, point)).sum()
+}
+
+/// Geometric median found by Weiszfeld iteration starting from the centroid.
+///
+/// When the estimate sits on crabs the step uses the Vardi–Zhang modification: crabs there are left out
+/// of the weighted mean and the step is shortened by their count over the pull of the others,
+/// the estimate is the median once that pull is no stronger than the crabs sitting on it.
+pub fn align_euclidean(crabs: &[Position]) -> Option<EuclideanAlignment> {
+    let dimension = crabs.first()?.0.len();
+    let mut point: Vec<f64> = (0..dimension)
+        .map(|idx| crabs.iter().map(|crab| crab.0[idx] as f64).sum::<f64>() / crabs.len() as f64)
+        .collect();
+    for _ in 0..WEISZFELD_ITERATIONS {
+        let mut numerator = vec![0.0; dimension];
+        let mut pull = vec![0.0; dimension];
+        let mut denominator = 0.0;
+        let mut coincident = 0.0;
+        for crab in crabs {
+            let distance = distance(crab, &point);
+            if distance < WEISZFELD_TOLERANCE {
+                coincident += 1.0;
+                continue;
+            }
+            for (idx, c) in crab.0.iter().enumerate() {
+                numerator[idx] += *c as f64 / distance;
+                pull[idx] += (*c as f64 - point[idx]) / distance;
+            }
+            denominator += 1.0 / distance;
+        }
+        if denominator == 0.0 {
+            break;
+        }
+        let pull = pull.iter().map(|p| p * p).sum::<f64>().sqrt();
+        if pull <= coincident {
+            break;
+        }
+        let shortening = coincident / pull;
+        let next: Vec<f64> = numerator.iter().zip(&point)
+            .map(|(n, p)| (1.0 - shortening) * n / denominator + shortening * p)
+            .collect();
+        let step = next.iter().zip(&point).map(|(n, p)| (n - p).abs()).fold(0.0, f64::max);
+        point = next;
+        if step < WEISZFELD_TOLERANCE {
+            break;
+        }
+    }
+    Some(EuclideanAlignment { fuel: euclidean_fuel(crabs, &point), position: point })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cost::{Linear, Triangular};
+    use crate::grid::{align_euclidean, align_separable, euclidean_fuel, parse_crabs, Position, separable_fuel};
+
+    #[test]
+    fn separable() {
+        let crabs = parse_crabs("0,0\n(4,1)\n1,5\n2,2\n9,3\n").unwrap();
+        assert_eq!(parse_crabs("1,2\n1,2,3"), Err(()));
+        assert_eq!(parse_crabs("1,a"), Err(()));
+        assert_eq!(align_separable(&[], &Linear), None);
+        for model in [&Linear as &dyn crate::cost::CostModel, &Triangular] {
+            let best = align_separable(&crabs, model).unwrap();
+            let brute_force = (0..=9)
+                .flat_map(|x| (0..=5).map(move |y| Position(vec![x, y])))
+                .map(|target| separable_fuel(&crabs, &target, model))
+                .min().unwrap();
+            assert_eq!(best.fuel, brute_force);
+            assert_eq!(separable_fuel(&crabs, &best.position, model), best.fuel);
+        }
+        assert_eq!(align_separable(&crabs, &Linear).unwrap().position, Position(vec![2, 2]));
+    }
+
+    #[test]
+    fn euclidean() {
+        let square = parse_crabs("0,0\n2,0\n0,2\n2,2").unwrap();
+        let centre = align_euclidean(&square).unwrap();
+        assert!((centre.position[0] - 1.0).abs() < 1e-6 && (centre.position[1] - 1.0).abs() < 1e-6);
+        assert!((centre.fuel - 4.0 * 2f64.sqrt()).abs() < 1e-6);
+        // Angle at the first crab is over 120 degrees so the median is the crab itself
+        let obtuse = parse_crabs("0,0,0\n10,1,0\n-10,1,0").unwrap();
+        let vertex = align_euclidean(&obtuse).unwrap();
+        assert!(vertex.position.iter().all(|coordinate| coordinate.abs() < 1e-6));
+        // Centroid lands on two crabs and plain Weiszfeld step from there overshoots
+        let stacked = parse_crabs("0,0\n0,0\n4,-9\n-2,-9\n3,-5\n-8,-4\n3,27").unwrap();
+        let median = align_euclidean(&stacked).unwrap();
+        assert!(median.fuel < euclidean_fuel(&stacked, &[0.0, 0.0]) - 0.1);
+        for (dx, dy) in [(0.01, 0.0), (-0.01, 0.0), (0.0, 0.01), (0.0, -0.01)] {
+            assert!(euclidean_fuel(&stacked, &[median.position[0] + dx, median.position[1] + dy]) >= median.fuel);
+        }
+        let crabs = parse_crabs("0,0\n4,1\n1,5\n2,2\n9,3").unwrap();
+        let median = align_euclidean(&crabs).unwrap();
+        for (dx, dy) in [(0.01, 0.0), (-0.01, 0.0), (0.0, 0.01), (0.0, -0.01)] {
+            assert!(euclidean_fuel(&crabs, &[median.position[0] + dx, median.position[1] + dy]) >= median.fuel);
+        }
+    }
+}
@@ -1,34 +1,66 @@
 #![feature(test)]
 
 use std::fs;
+use std::fs::File;
 use std::ops::Div;
+use custom_error::custom_error;
+use crate::cost::{Convex, Linear, Quadratic, total_fuel, Triangular, Weighted};
+use crate::curve::FuelCurve;
+use crate::grid::{align_euclidean, align_separable, parse_crabs, Position, separable_fuel};
+use crate::optimizer::{align, optimal_range};
+
+mod cost;
+mod optimizer;
+mod grid;
+mod curve;
+
+custom_error! {
+    #[derive(PartialEq)]
+    pub FuelError
+    EmptyInput = "No crab positions given",
+    EmptyRange = "Range of target positions is empty",
+    UnknownShape = "Optimal positions of a cost without known shape need a fuel curve",
+}
 
 fn main() {
-    {
-        let str = fs::read_to_string("day7/input_data.dat").unwrap();
-        let horizontal_pos: Vec<i64> = str.split(',')
-            .into_iter()
-            .filter_map(|pos_str| pos_str.parse().ok())
-            .collect();
-        let fuel = horizontal_pos.iter()
-            .map(|crab_pos| {
-                horizontal_pos.iter().fold(0, |acc, pos| acc + (pos - crab_pos).abs())
-            })
-            .min()
-            .unwrap();
-        println!("Part1 Minimum fuel: {}", fuel);
+    let str = fs::read_to_string("day7/input_data.dat").unwrap();
+    let horizontal_pos: Vec<i64> = str.split(',')
+        .into_iter()
+        .filter_map(|pos_str| pos_str.parse().ok())
+        .collect();
+    println!("Part1 Minimum fuel: {}", align(&horizontal_pos, &Linear).unwrap().fuel);
+    println!("Part2 Minimum fuel: {}", align(&horizontal_pos, &Triangular).unwrap().fuel);
+    println!("Part1 optimal positions: {:?}", optimal_range(&horizontal_pos, &Linear).unwrap());
+    let curve = FuelCurve::over_crabs(&horizontal_pos, &Triangular).unwrap();
+    println!("Part2 optimal positions: {:?}", curve.optimal_positions());
+    println!("Part2 positions within 1% of minimum: {}", curve.within(curve.minimum() / 100).len());
+    println!("Least squares fuel: {}", align(&horizontal_pos, &Quadratic).unwrap().fuel);
+    println!("Cubic fuel: {}", align(&horizontal_pos, &Convex(|distance: i64| distance.abs().pow(3))).unwrap().fuel);
+    let mut grouped: Vec<(i64, i64)> = vec![];
+    let mut sorted = horizontal_pos.clone();
+    sorted.sort_unstable();
+    for pos in sorted {
+        match grouped.last_mut() {
+            Some((last, count)) if *last == pos => *count += 1,
+            _ => grouped.push((pos, 1)),
+        }
     }
-    {
-        let str = fs::read_to_string("day7/input_data.dat").unwrap();
-        let horizontal_pos: Vec<i64> = str.split(',')
-            .into_iter()
-            .filter_map(|pos_str| pos_str.parse().ok())
-            .collect();
-        let fuel = (*horizontal_pos.iter().min().unwrap()..=*horizontal_pos.iter().max().unwrap()).into_iter()
-            .map(|crab_pos| {
-                horizontal_pos.iter().fold(0, |acc, pos| acc + calc_cost((pos - crab_pos).abs()))
-            }).min().unwrap();
-        println!("Part2 Minimum fuel: {}", fuel);
+    let (distinct, counts): (Vec<i64>, Vec<i64>) = grouped.into_iter().unzip();
+    println!("Part2 fuel with crabs grouped by position: {}",
+             align(&distinct, &Weighted { weights: counts, model: Triangular }).unwrap().fuel);
+    // Grid crabs come from `x,y[,z...]` file given as first argument, puzzle crabs sit on a line otherwise
+    let grid_crabs = match std::env::args().nth(1) {
+        Some(path) => parse_crabs(&fs::read_to_string(path).unwrap()).unwrap(),
+        None => horizontal_pos.iter().map(|pos| Position(vec![*pos])).collect(),
+    };
+    let manhattan = align_separable(&grid_crabs, &Linear).unwrap();
+    println!("Grid Manhattan meeting point: {:?} fuel: {}", manhattan.position.0, manhattan.fuel);
+    println!("Grid Triangular fuel: {} at Manhattan meeting point: {}",
+             align_separable(&grid_crabs, &Triangular).unwrap().fuel, separable_fuel(&grid_crabs, &manhattan.position, &Triangular));
+    let euclidean = align_euclidean(&grid_crabs).unwrap();
+    println!("Grid Euclidean meeting point: {:.3?} fuel: {:.3}", euclidean.position, euclidean.fuel);
+    if let Ok(file) = File::create("day7/fuel_curve.csv") {
+        curve.write_csv(file).unwrap();
     }
 }
 
@@ -41,38 +73,16 @@ pub fn calc_cost_slow(movement: i64) -> i64 {
 }
 
 pub fn total_fuel_used(start_pos: &[i64], end_pos: i64) -> i64 {
-    start_pos.iter().fold(0, |acc, pos| acc + calc_cost_slow((pos - end_pos).abs()))
+    total_fuel(start_pos, end_pos, &Triangular) as i64
 }
 
-pub fn get_least_eval_in_range<T: IntoIterator<Item=i64>, F: Fn(i64) -> i64>(iterable: T, fun: F) -> Result<i64, ()> {
-    let mut iter = iterable.into_iter();
-    let mut previous = fun(iter.next().ok_or(())?);
-    for target in iter {
-        let cur = fun(target);
-        if previous < cur {
-            return Ok(previous);
-        }
-        previous = cur;
-    }
-    Err(())
+/// Least value of `fun` over the whole range, monotonic ranges included
+pub fn get_least_eval_in_range<T: IntoIterator<Item=i64>, F: Fn(i64) -> i64>(iterable: T, fun: F) -> Result<i64, FuelError> {
+    iterable.into_iter().map(fun).min().ok_or(FuelError::EmptyRange)
 }
 
-///Assumes that the function has only one minima
-pub fn get_minimum_used_fuel(start_pos: &[i64]) -> Result<i64, ()> {
-    let avg_pos = (start_pos.iter().sum::<i64>() as f64 / start_pos.len() as f64).round() as i64;
-    if total_fuel_used(start_pos, avg_pos - 1) > total_fuel_used(start_pos, avg_pos) {
-        //go right
-        return get_least_eval_in_range(
-            avg_pos..=*start_pos.iter().max().unwrap(),
-            |target_pos| total_fuel_used(start_pos, target_pos));
-    } else if total_fuel_used(start_pos, avg_pos + 1) > total_fuel_used(start_pos, avg_pos) {
-        //go left
-        return get_least_eval_in_range(
-            (0..avg_pos).rev(),
-            |target_pos| total_fuel_used(start_pos, target_pos));
-    } else {
-        Ok(total_fuel_used(start_pos, avg_pos))
-    }
+pub fn get_minimum_used_fuel(start_pos: &[i64]) -> Result<i64, FuelError> {
+    align(start_pos, &Triangular).map(|alignment| alignment.fuel as i64).ok_or(FuelError::EmptyInput)
 }
 
 #[cfg(test)]
@@ -81,7 +91,7 @@ mod day7test {
 
     use std::fs;
     use test::Bencher;
-    use crate::{calc_cost, calc_cost_slow, get_minimum_used_fuel};
+    use crate::{calc_cost, calc_cost_slow, FuelError, get_least_eval_in_range, get_minimum_used_fuel, total_fuel_used};
 
     #[test]
     fn part1() {
@@ -116,6 +126,10 @@ mod day7test {
             }).min().unwrap();
         assert_eq!(fuel, 168);
         assert_eq!(get_minimum_used_fuel(horizontal_pos.as_slice()).unwrap(), 168);
+        assert_eq!(get_least_eval_in_range(0..=16, |target| total_fuel_used(&horizontal_pos, target)), Ok(168));
+        assert_eq!(get_least_eval_in_range(10..=16, |target| total_fuel_used(&horizontal_pos, target)), Ok(total_fuel_used(&horizontal_pos, 10)));
+        assert_eq!(get_least_eval_in_range(1..1, calc_cost), Err(FuelError::EmptyRange));
+        assert_eq!(get_minimum_used_fuel(&[]), Err(FuelError::EmptyInput));
     }
 
     #[bench]
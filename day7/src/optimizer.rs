@@ -0,0 +1,285 @@
+use std::ops::RangeInclusive;
+use crate::cost::{CostModel, Shape, total_fuel};
+use crate::FuelError;
+
+/// Position all crabs move to and fuel they need for it
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Alignment {
+    pub position: i64,
+    pub fuel: i128,
+}
+
+/// Distinct sorted positions with prefix sums of crab weights, evaluates polynomial costs in O(log n)
+pub struct SortedCrabs {
+    /// Distinct positions with total weight of crabs there
+    groups: Vec<(i64, i128)>,
+    /// `weights[k]`, `sums[k]` and `squares[k]` are weighted sums over the first `k` groups
+    weights: Vec<i128>,
+    sums: Vec<i128>,
+    squares: Vec<i128>,
+}
+
+impl SortedCrabs {
+    pub fn new(positions: &[i64]) -> Self {
+        SortedCrabs::weighted(positions, |_| 1)
+    }
+    pub fn weighted(positions: &[i64], weight: impl Fn(usize) -> i64) -> Self {
+        let mut crabs: Vec<(i64, i128)> = positions.iter().enumerate()
+            .map(|(crab, pos)| (*pos, weight(crab) as i128))
+            .collect();
+        crabs.sort_unstable();
+        let mut groups: Vec<(i64, i128)> = vec![];
+        for (pos, weight) in crabs {
+            match groups.last_mut() {
+                Some((last, total)) if *last == pos => *total += weight,
+                _ => groups.push((pos, weight)),
+            }
+        }
+        let (mut weights, mut sums, mut squares) = (vec![0], vec![0], vec![0]);
+        groups.iter().for_each(|(pos, weight)| {
+            let pos = *pos as i128;
+            weights.push(weights.last().unwrap() + weight);
+            sums.push(sums.last().unwrap() + weight * pos);
+            squares.push(squares.last().unwrap() + weight * pos * pos);
+        });
+        SortedCrabs { groups, weights, sums, squares }
+    }
+
+    fn total_weight(&self) -> i128 {
+        *self.weights.last().unwrap()
+    }
+    fn min(&self) -> Option<i64> {
+        self.groups.first().map(|(pos, _)| *pos)
+    }
+    fn max(&self) -> Option<i64> {
+        self.groups.last().map(|(pos, _)| *pos)
+    }
+    /// Position of crab with index `idx` in sorted order, crabs of weight 1 assumed
+    fn nth(&self, idx: usize) -> Option<i64> {
+        let group = self.weights.partition_point(|weight| *weight <= idx as i128);
+        self.groups.get(group.checked_sub(1)?).map(|(pos, _)| *pos)
+    }
+
+    /// Weighted sums of distances and squared distances to target
+    fn moments(&self, target: i64) -> (i128, i128) {
+        let n = self.groups.len();
+        let left = self.groups.partition_point(|(pos, _)| *pos < target);
+        let x = target as i128;
+        let (left_weight, right_weight) = (self.weights[left], self.weights[n] - self.weights[left]);
+        let (left_sum, right_sum) = (self.sums[left], self.sums[n] - self.sums[left]);
+        let distances = (x * left_weight - left_sum) + (right_sum - x * right_weight);
+        let squares = x * x * self.weights[n] - 2 * x * self.sums[n] + self.squares[n];
+        (distances, squares)
+    }
+    pub fn linear(&self, target: i64) -> i128 {
+        self.moments(target).0
+    }
+    pub fn triangular(&self, target: i64) -> i128 {
+        let (distances, squares) = self.moments(target);
+        (squares + distances) / 2
+    }
+    pub fn quadratic(&self, target: i64) -> i128 {
+        self.moments(target).1
+    }
+    /// Total fuel with prefix sums for polynomial costs, other costs are summed over distinct positions
+    pub fn fuel<C: CostModel + ?Sized>(&self, target: i64, model: &C) -> i128 {
+        match model.cost_shape() {
+            Shape::Linear => self.linear(target),
+            Shape::Triangular => self.triangular(target),
+            Shape::Quadratic => self.quadratic(target),
+            Shape::Convex | Shape::Unknown => self.groups.iter()
+                .map(|(pos, weight)| weight * model.cost(pos - target) as i128)
+                .sum(),
+        }
+    }
+    /// Mean rounded down
+    fn mean_floor(&self) -> i64 {
+        self.sums[self.groups.len()].div_euclid(self.total_weight()) as i64
+    }
+
+    fn best_of(&self, candidates: impl Iterator<Item=i64>, fuel: impl Fn(i64) -> i128) -> Option<Alignment> {
+        let (min, max) = (self.min()?, self.max()?);
+        candidates
+            .filter(|position| (min..=max).contains(position))
+            .map(|position| Alignment { position, fuel: fuel(position) })
+            .min_by_key(|alignment| (alignment.fuel, alignment.position))
+    }
+}
+
+/// Cheapest position between leftmost and rightmost crab, the leftmost one when several are equal.
+///
+/// Strategy depends on cost shape: median for linear cost, mean ±1 for triangular and quadratic,
+/// binary search on fuel difference of neighbouring positions over weighted prefix sums for other convex costs
+/// and brute force when nothing is known.
+pub fn align<C: CostModel + ?Sized>(positions: &[i64], model: &C) -> Option<Alignment> {
+    let crabs = SortedCrabs::new(positions);
+    let mean = if positions.is_empty() { 0 } else { crabs.mean_floor() };
+    match model.shape() {
+        Shape::Linear => {
+            let median = crabs.nth(positions.len().checked_sub(1)? / 2)?;
+            Some(Alignment { position: median, fuel: crabs.linear(median) })
+        }
+        // Optimum of continuous relaxation lies within half a step from the mean
+        Shape::Triangular => crabs.best_of(mean - 1..=mean + 2, |position| crabs.triangular(position)),
+        Shape::Quadratic => crabs.best_of(mean..=mean + 1, |position| crabs.quadratic(position)),
+        Shape::Convex => align_convex(positions, model),
+        Shape::Unknown => align_brute_force(positions, model),
+    }
+}
+
+/// Total fuel as a function of target, O(log n) per call for (weighted) polynomial costs
+/// and O(distinct positions) otherwise
+pub fn fuel_evaluator<'a, C: CostModel + ?Sized>(positions: &'a [i64], model: &'a C) -> impl Fn(i64) -> i128 + 'a {
+    let crabs = SortedCrabs::weighted(positions, |crab| model.weight(crab));
+    move |target| crabs.fuel(target, model)
+}
+
+/// All positions attaining minimal fuel, they form a range because total fuel is convex
+pub fn optimal_range<C: CostModel + ?Sized>(positions: &[i64], model: &C) -> Result<RangeInclusive<i64>, FuelError> {
+    if model.shape() == Shape::Unknown {
+        return Err(FuelError::UnknownShape);
+    }
+    let best = align(positions, model).ok_or(FuelError::EmptyInput)?;
+    let fuel = fuel_evaluator(positions, model);
+    let (mut low, mut high) = (best.position, *positions.iter().max().unwrap());
+    while low < high {
+        let middle = low + (high - low + 1) / 2;
+        if fuel(middle) == best.fuel {
+            low = middle;
+        } else {
+            high = middle - 1;
+        }
+    }
+    Ok(best.position..=low)
+}
+
+/// Leftmost position where fuel stops decreasing.
+///
+/// Every probe is O(log n) for weighted linear, triangular and quadratic costs and O(distinct positions)
+/// for other convex costs, which have no closed form to build prefix sums of.
+pub fn align_convex<C: CostModel + ?Sized>(positions: &[i64], model: &C) -> Option<Alignment> {
+    let crabs = SortedCrabs::weighted(positions, |crab| model.weight(crab));
+    let (mut low, mut high) = (crabs.min()?, crabs.max()?);
+    while low < high {
+        let middle = low + (high - low) / 2;
+        if crabs.fuel(middle, model) <= crabs.fuel(middle + 1, model) {
+            high = middle;
+        } else {
+            low = middle + 1;
+        }
+    }
+    Some(Alignment { position: low, fuel: crabs.fuel(low, model) })
+}
+
+/// Checks every position in range, O(n · range)
+pub fn align_brute_force<C: CostModel + ?Sized>(positions: &[i64], model: &C) -> Option<Alignment> {
+    let (min, max) = (*positions.iter().min()?, *positions.iter().max()?);
+    (min..=max)
+        .map(|position| Alignment { position, fuel: total_fuel(positions, position, model) })
+        .min_by_key(|alignment| alignment.fuel)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cost::{Convex, CostModel, Linear, Quadratic, Shape, total_fuel, Triangular, Weighted};
+    use crate::FuelError;
+    use crate::optimizer::{align, align_brute_force, Alignment, fuel_evaluator, optimal_range, SortedCrabs};
+
+    static CRABS: [i64; 10] = [16, 1, 2, 0, 4, 2, 7, 1, 2, 14];
+
+    /// Deterministic pseudo random positions
+    fn crabs(count: usize, range: i64, seed: u64) -> Vec<i64> {
+        let mut state = seed;
+        (0..count).map(|_| {
+            state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            ((state >> 33) % range as u64) as i64
+        }).collect()
+    }
+
+    #[test]
+    fn test_data() {
+        assert_eq!(align(&CRABS, &Linear), Some(Alignment { position: 2, fuel: 37 }));
+        assert_eq!(align(&CRABS, &Triangular), Some(Alignment { position: 5, fuel: 168 }));
+        assert_eq!(align(&CRABS, &Quadratic).unwrap().position, 5);
+        let heavy_last = Weighted { weights: vec![1, 1, 1, 1, 1, 1, 1, 1, 1, 100], model: Linear };
+        assert_eq!(align(&CRABS, &heavy_last).unwrap().position, 14);
+        assert_eq!(align(&CRABS, &|distance: i64| distance.abs().min(3)), Some(Alignment { position: 2, fuel: 15 }));
+        assert_eq!(align(&[], &Linear), None);
+        assert_eq!(align(&[], &Triangular), None);
+    }
+
+    #[test]
+    fn prefix_sums() {
+        let crabs = SortedCrabs::new(&CRABS);
+        for target in -3..20 {
+            assert_eq!(crabs.linear(target), (0..CRABS.len()).map(|idx| Linear.cost(CRABS[idx] - target) as i128).sum());
+            assert_eq!(crabs.triangular(target), (0..CRABS.len()).map(|idx| Triangular.cost(CRABS[idx] - target) as i128).sum());
+            assert_eq!(crabs.quadratic(target), (0..CRABS.len()).map(|idx| Quadratic.cost(CRABS[idx] - target) as i128).sum());
+        }
+    }
+
+    #[test]
+    fn agrees_with_brute_force() {
+        for seed in 0..20 {
+            let positions = crabs(1 + seed as usize * 3, 60, seed);
+            let quartic = Convex(|distance: i64| distance.pow(4));
+            let weighted = Weighted { weights: crabs(positions.len(), 5, seed + 100), model: Triangular };
+            assert_eq!(align(&positions, &Linear).unwrap().fuel, align_brute_force(&positions, &Linear).unwrap().fuel);
+            assert_eq!(align(&positions, &Triangular), align_brute_force(&positions, &Triangular));
+            assert_eq!(align(&positions, &Quadratic), align_brute_force(&positions, &Quadratic));
+            assert_eq!(align(&positions, &quartic), align_brute_force(&positions, &quartic));
+            assert_eq!(align(&positions, &weighted), align_brute_force(&positions, &weighted));
+        }
+    }
+
+    #[test]
+    fn optimal_ranges() {
+        assert_eq!(optimal_range(&CRABS, &Linear), Ok(2..=2));
+        assert_eq!(optimal_range(&[1, 5], &Linear), Ok(1..=5));
+        assert_eq!(optimal_range(&[0, 1], &Triangular), Ok(0..=1));
+        assert_eq!(optimal_range(&[0, 7, 20], &Convex(|distance: i64| distance.abs().max(3))), Ok(4..=10));
+        assert_eq!(optimal_range(&[], &Linear), Err(FuelError::EmptyInput));
+        assert_eq!(optimal_range(&CRABS, &|distance: i64| distance), Err(FuelError::UnknownShape));
+        let positions = crabs(1_000_000, 1_000_000_000, 3);
+        let range = optimal_range(&positions, &Linear).unwrap();
+        assert!(range.end() - range.start() < 10_000);
+    }
+
+    #[test]
+    fn weighted_prefix_sums() {
+        let weights = vec![3, 0, 2, 1, 5, 1, 1, 4, 2, 1];
+        let crabs = SortedCrabs::weighted(&CRABS, |crab| weights[crab]);
+        let quartic = Convex(|distance: i64| distance.pow(4));
+        assert_eq!(Weighted { weights: weights.clone(), model: Triangular }.cost_shape(), Shape::Triangular);
+        for target in -3..20 {
+            assert_eq!(crabs.fuel(target, &Weighted { weights: weights.clone(), model: Linear }),
+                       total_fuel(&CRABS, target, &Weighted { weights: weights.clone(), model: Linear }));
+            assert_eq!(crabs.fuel(target, &Weighted { weights: weights.clone(), model: Triangular }),
+                       total_fuel(&CRABS, target, &Weighted { weights: weights.clone(), model: Triangular }));
+            assert_eq!(crabs.fuel(target, &Weighted { weights: weights.clone(), model: quartic }),
+                       total_fuel(&CRABS, target, &Weighted { weights: weights.clone(), model: quartic }));
+        }
+    }
+
+    #[test]
+    fn million_weighted_crabs() {
+        let positions = crabs(1_000_000, 1_000_000_000, 11);
+        let weighted = Weighted { weights: crabs(positions.len(), 10, 12), model: Triangular };
+        let best = align(&positions, &weighted).unwrap();
+        let fuel = fuel_evaluator(&positions, &weighted);
+        assert_eq!(fuel(best.position), best.fuel);
+        assert!(fuel(best.position - 1) >= best.fuel && fuel(best.position + 1) >= best.fuel);
+    }
+
+    #[test]
+    fn million_crabs() {
+        let positions = crabs(1_000_000, 1_000_000_000, 7);
+        let linear = align(&positions, &Linear).unwrap();
+        let triangular = align(&positions, &Triangular).unwrap();
+        let crabs = SortedCrabs::new(&positions);
+        for offset in [-1, 1] {
+            assert!(crabs.linear(linear.position + offset) >= linear.fuel);
+            assert!(crabs.triangular(triangular.position + offset) >= triangular.fuel);
+        }
+    }
+}
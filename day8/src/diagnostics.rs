@@ -0,0 +1,236 @@
+use std::cmp::Reverse;
+use crate::display::DisplayDefinition;
+use crate::solver::{DecodeError, mappings, parse_pattern, Pattern, WireMapping};
+
+/// Mappings explored for every fault before an entry is declared too ambiguous
+const DIAGNOSIS_LIMIT: usize = 1024;
+
+/// Segments whose wire is always active or never active
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct Fault {
+    pub stuck_on: Pattern,
+    pub stuck_off: Pattern,
+}
+
+impl Fault {
+    pub fn count(&self) -> u32 {
+        (self.stuck_on | self.stuck_off).count_ones()
+    }
+    /// Segments a glyph lights on faulty display
+    pub fn apply(&self, glyph: Pattern) -> Pattern {
+        glyph & !self.stuck_off | self.stuck_on
+    }
+}
+
+/// Wiring together with faults that explain all patterns of an entry
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnosis {
+    pub mapping: WireMapping,
+    pub fault: Fault,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostics {
+    /// Explanations with the fewest faults
+    pub diagnoses: Vec<Diagnosis>,
+    /// Glyphs every output may show, the most likely first
+    pub candidates: Vec<Vec<char>>,
+}
+
+impl Diagnostics {
+    /// Most likely glyph of every output
+    pub fn reading(&self) -> String {
+        self.candidates.iter().filter_map(|glyphs| glyphs.first()).collect()
+    }
+    /// Reading is certain when every output has a single candidate
+    pub fn is_certain(&self) -> bool {
+        self.candidates.iter().all(|glyphs| glyphs.len() == 1)
+    }
+    pub fn suspected_stuck_on(&self) -> Pattern {
+        self.diagnoses.iter().fold(0, |segments, diagnosis| segments | diagnosis.fault.stuck_on)
+    }
+    pub fn suspected_stuck_off(&self) -> Pattern {
+        self.diagnoses.iter().fold(0, |segments, diagnosis| segments | diagnosis.fault.stuck_off)
+    }
+}
+
+fn gcd(lhs: u128, rhs: u128) -> u128 {
+    if rhs == 0 { lhs } else { gcd(rhs, lhs % rhs) }
+}
+
+/// All ways to pick `count` faulty segments out of `segments`, each stuck either on or off
+fn faults(segments: usize, count: usize) -> Vec<Fault> {
+    if count == 0 {
+        return vec![Fault::default()];
+    }
+    if segments < count {
+        return vec![];
+    }
+    let last = 1 << (segments - 1);
+    let mut with_last: Vec<Fault> = faults(segments - 1, count - 1).into_iter()
+        .flat_map(|fault| [
+            Fault { stuck_on: fault.stuck_on | last, ..fault },
+            Fault { stuck_off: fault.stuck_off | last, ..fault },
+        ])
+        .collect();
+    with_last.extend(faults(segments - 1, count));
+    with_last
+}
+
+impl DisplayDefinition {
+    /// Explains entry with up to `max_faults` stuck wires, fewer faults are more likely.
+    ///
+    /// Entries may miss some of the glyphs, outputs are then voted on by all fitting mappings.
+    pub fn diagnose(&self, entry: &str, max_faults: usize) -> Result<Diagnostics, DecodeError> {
+        let (signals, outputs) = entry.split_once('|').ok_or(DecodeError::MissingSeparator)?;
+        let patterns = signals.split_whitespace().chain(outputs.split_whitespace())
+            .map(|pattern| parse_pattern(pattern, self.segments()))
+            .collect::<Result<Vec<Pattern>, DecodeError>>()?;
+        let outputs = &patterns[patterns.len() - outputs.split_whitespace().count()..];
+        // Stuck wires are active in all patterns or in none of them
+        let always = patterns.iter().fold(Pattern::MAX, |always, pattern| always & pattern).count_ones();
+        let never = self.segments() as u32 - patterns.iter().fold(0, |ever, pattern| ever | pattern).count_ones();
+        for count in 0..=max_faults.min(self.segments()) {
+            let mut diagnoses = vec![];
+            let plausible = faults(self.segments(), count).into_iter()
+                .filter(|fault| fault.stuck_on.count_ones() <= always && fault.stuck_off.count_ones() <= never);
+            for fault in plausible {
+                let glyphs: Vec<Pattern> = self.glyphs().iter().map(|(_, glyph)| fault.apply(*glyph)).collect();
+                let found = mappings(&patterns, &glyphs, self.segments(), DIAGNOSIS_LIMIT);
+                if diagnoses.len() + found.len() >= DIAGNOSIS_LIMIT {
+                    return Err(DecodeError::AmbiguousMapping);
+                }
+                diagnoses.extend(found.into_iter().map(|mapping| Diagnosis { mapping, fault }));
+            }
+            if !diagnoses.is_empty() {
+                let candidates = outputs.iter().map(|output| self.vote(&diagnoses, *output)).collect();
+                return Ok(Diagnostics { diagnoses, candidates });
+            }
+        }
+        Err(DecodeError::NoMapping)
+    }
+
+    /// Glyphs output may show ordered by share of diagnoses backing them.
+    ///
+    /// Shares are counted exactly and equal shares are ordered by glyph, so the order of the glyph table does not matter.
+    fn vote(&self, diagnoses: &[Diagnosis], output: Pattern) -> Vec<char> {
+        let allowed: Vec<Vec<usize>> = diagnoses.iter()
+            .map(|diagnosis| {
+                let segments = diagnosis.mapping.apply(output);
+                (0..self.glyphs().len())
+                    .filter(|idx| diagnosis.fault.apply(self.glyphs()[*idx].1) == segments)
+                    .collect()
+            })
+            .collect();
+        // Every diagnosis splits `scale` votes evenly among glyphs it allows, only counts that occur need to divide it
+        let mut counts: Vec<u128> = allowed.iter().map(|matching| matching.len() as u128).filter(|count| *count > 0).collect();
+        counts.sort_unstable();
+        counts.dedup();
+        let scale = counts.into_iter().fold(1, |scale, count| scale / gcd(scale, count) * count);
+        let mut votes = vec![0; self.glyphs().len()];
+        for matching in allowed {
+            matching.iter().for_each(|idx| votes[*idx] += scale / matching.len() as u128);
+        }
+        let mut ranked: Vec<(u128, char)> = votes.into_iter().zip(self.glyphs())
+            .filter(|(votes, _)| *votes > 0)
+            .map(|(votes, (glyph, _))| (votes, *glyph))
+            .collect();
+        ranked.sort_by_key(|(votes, glyph)| (Reverse(*votes), *glyph));
+        ranked.into_iter().map(|(_, glyph)| glyph).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use crate::diagnostics::{Diagnosis, Fault, faults};
+    use crate::display::DisplayDefinition;
+    use crate::solver::{DecodeError, WireMapping};
+
+    /// Removes wire from every pattern or adds it to every one
+    fn break_wire(entry: &str, wire: char, stuck_on: bool) -> String {
+        entry.split(' ')
+            .map(|pattern| match pattern {
+                "|" => pattern.to_string(),
+                _ if stuck_on && !pattern.contains(wire) => format!("{}{}", pattern, wire),
+                _ if stuck_on => pattern.to_string(),
+                _ => pattern.replace(wire, ""),
+            })
+            .collect::<Vec<String>>()
+            .join(" ")
+    }
+
+    #[test]
+    fn fault_enumeration() {
+        assert_eq!(faults(7, 0), vec![Fault::default()]);
+        assert_eq!(faults(7, 1).len(), 14);
+        assert_eq!(faults(7, 2).len(), 84);
+        assert_eq!(Fault { stuck_on: 0b001, stuck_off: 0b100 }.apply(0b110), 0b011);
+    }
+
+    #[test]
+    fn healthy_and_partial_entries() {
+        let display = DisplayDefinition::seven_segment();
+        let example = "acedgfb cdfbe gcdfa fbcad dab cefabd cdfgeb eafb cagedb ab | cdfeb fcadb cdfeb cdbaf";
+        let diagnostics = display.diagnose(example, 2).unwrap();
+        assert_eq!(diagnostics.diagnoses.len(), 1);
+        assert_eq!(diagnostics.reading(), "5353");
+        assert_eq!((diagnostics.suspected_stuck_on(), diagnostics.suspected_stuck_off()), (0, 0));
+        // Only `1`, `7` and `4` given, the size 5 output is 2 or 5 depending on wiring
+        let partial = display.diagnose("ab dab eafb | ab eafb dab fbcad", 0).unwrap();
+        assert_eq!(partial.diagnoses.len(), 2);
+        assert_eq!(partial.reading(), "1473");
+        assert!(partial.is_certain());
+        let partial = display.diagnose("ab dab | acdeg", 0).unwrap();
+        assert!(partial.candidates[0].len() > 1);
+        assert!(!partial.is_certain());
+        assert_eq!(display.diagnose("ab ac | bc", 0), Err(DecodeError::NoMapping));
+    }
+
+    #[test]
+    fn ties_do_not_depend_on_glyph_order() {
+        let display = DisplayDefinition::seven_segment();
+        let reversed = DisplayDefinition::new(7, display.glyphs().iter().rev().copied().collect()).unwrap();
+        let partial = display.diagnose("ab dab | acdeg", 0).unwrap();
+        assert_eq!(partial.candidates, vec![vec!['2', '5']]);
+        assert_eq!(reversed.diagnose("ab dab | acdeg", 0).unwrap().candidates, partial.candidates);
+        assert_eq!(partial.reading(), "2");
+    }
+
+    #[test]
+    fn votes_over_large_glyph_tables() {
+        let glyphs = (0..95).map(|idx| ((b' ' + idx) as char, idx as u32 + 1)).collect();
+        let display = DisplayDefinition::new(7, glyphs).unwrap();
+        let identity = WireMapping((0..7).collect());
+        // Lower six segments dead, any glyph lighting the top one looks the same
+        let dead = Diagnosis { mapping: identity.clone(), fault: Fault { stuck_on: 0, stuck_off: 0b0111111 } };
+        let healthy = Diagnosis { mapping: identity, fault: Fault::default() };
+        let candidates = display.vote(&[dead, healthy], 0b1000000);
+        assert_eq!(candidates.len(), 32);
+        assert_eq!(candidates[..3], ['_', '`', 'a']);
+        assert_eq!(candidates.last(), Some(&'~'));
+    }
+
+    #[test]
+    fn stuck_wires() {
+        let display = DisplayDefinition::seven_segment();
+        let wiring = WireMapping::random(7, &mut StdRng::seed_from_u64(8));
+        let entry = display.encode(&wiring, "1347", &mut StdRng::seed_from_u64(8)).unwrap();
+        // Wire driving bottom left segment `e` is dead
+        let dead = (b'a' + wiring.0.iter().position(|segment| *segment == 4).unwrap() as u8) as char;
+        let diagnostics = display.diagnose(&break_wire(&entry, dead, false), 1).unwrap();
+        assert_eq!(diagnostics.reading(), "1347");
+        assert!(diagnostics.is_certain());
+        assert_eq!(diagnostics.suspected_stuck_off(), 1 << 4);
+        assert_eq!(display.diagnose(&break_wire(&entry, dead, false), 0), Err(DecodeError::NoMapping));
+        // Wire driving top segment `a` is always active, `1` looks like `7`
+        let live = (b'a' + wiring.0.iter().position(|segment| *segment == 0).unwrap() as u8) as char;
+        let diagnostics = display.diagnose(&break_wire(&entry, live, true), 1).unwrap();
+        assert_eq!(diagnostics.suspected_stuck_on() & 1, 1);
+        assert!(diagnostics.diagnoses.iter().all(|diagnosis| diagnosis.fault.count() == 1));
+        assert_eq!(diagnostics.candidates[0], vec!['1', '7']);
+        assert_eq!(diagnostics.candidates[3], vec!['1', '7']);
+        assert_eq!(&diagnostics.reading()[1..3], "34");
+    }
+}
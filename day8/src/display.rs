@@ -0,0 +1,192 @@
+use crate::solver::{DecodeError, mappings, parse_pattern, Pattern, solve, WireMapping};
+
+/// Segments of standard seven segment digits 0-9
+const SEVEN_SEGMENT: [(char, Pattern); 10] = [
+    ('0', 0b1110111), ('1', 0b0100100), ('2', 0b1011101), ('3', 0b1101101), ('4', 0b0101110),
+    ('5', 0b1101011), ('6', 0b1111011), ('7', 0b0100101), ('8', 0b1111111), ('9', 0b1101111),
+];
+
+/// Alphanumeric font, bits are segments A B C D E F G1 G2 H J K L M N
+const FOURTEEN_SEGMENT: [(char, Pattern); 36] = [
+    ('0', 0x0C3F), ('1', 0x0006), ('2', 0x00DB), ('3', 0x008F), ('4', 0x00E6),
+    ('5', 0x2069), ('6', 0x00FD), ('7', 0x0007), ('8', 0x00FF), ('9', 0x00EF),
+    ('A', 0x00F7), ('B', 0x128F), ('C', 0x0039), ('D', 0x120F), ('E', 0x00F9),
+    ('F', 0x0071), ('G', 0x00BD), ('H', 0x00F6), ('I', 0x1209), ('J', 0x001E),
+    ('K', 0x2470), ('L', 0x0038), ('M', 0x0536), ('N', 0x2136), ('O', 0x003F),
+    ('P', 0x00F3), ('Q', 0x203F), ('R', 0x20F3), ('S', 0x00ED), ('T', 0x1201),
+    ('U', 0x003E), ('V', 0x0C30), ('W', 0x2836), ('X', 0x2D00), ('Y', 0x1500),
+    ('Z', 0x0C09),
+];
+
+/// Mappings explored before a reading is declared ambiguous
+const MAPPING_LIMIT: usize = 1024;
+
+/// Segments of a display and glyphs it can show, wire and segment `i` are both named `'a' + i`
+#[derive(Debug, Clone, PartialEq)]
+pub struct DisplayDefinition {
+    segments: usize,
+    glyphs: Vec<(char, Pattern)>,
+}
+
+impl DisplayDefinition {
+    /// Every glyph has to fit in the segments and light a distinct set of them
+    pub fn new(segments: usize, glyphs: Vec<(char, Pattern)>) -> Result<Self, DecodeError> {
+        if segments > Pattern::BITS as usize {
+            return Err(DecodeError::InvalidDisplay { reason: format!("{} segments do not fit in a pattern", segments) });
+        }
+        for (idx, (glyph, pattern)) in glyphs.iter().enumerate() {
+            // Shifting by all 32 bits of a pattern would overflow, nothing lies outside a full display
+            if pattern.checked_shr(segments as u32).is_some_and(|outside| outside != 0) {
+                return Err(DecodeError::InvalidDisplay { reason: format!("{} uses segments outside display", glyph) });
+            }
+            if glyphs[..idx].iter().any(|(_, other)| other == pattern) {
+                return Err(DecodeError::InvalidDisplay { reason: format!("{} is not distinguishable", glyph) });
+            }
+        }
+        Ok(DisplayDefinition { segments, glyphs })
+    }
+
+    /// Glyph table with segments given as letters, `[('1', "cf"), ...]`
+    pub fn from_table(segments: usize, table: &[(char, &str)]) -> Result<Self, DecodeError> {
+        let glyphs = table.iter()
+            .map(|(glyph, pattern)| parse_pattern(pattern, segments).map(|pattern| (*glyph, pattern)))
+            .collect::<Result<Vec<_>, DecodeError>>()?;
+        DisplayDefinition::new(segments, glyphs)
+    }
+
+    pub fn seven_segment() -> Self {
+        DisplayDefinition { segments: 7, glyphs: SEVEN_SEGMENT.to_vec() }
+    }
+    pub fn fourteen_segment() -> Self {
+        DisplayDefinition { segments: 14, glyphs: FOURTEEN_SEGMENT.to_vec() }
+    }
+    /// Fourteen segment font with top and bottom bars split in halves that light together
+    pub fn sixteen_segment() -> Self {
+        let split = |pattern: Pattern| (0..14)
+            .filter(|segment| pattern & 1 << segment != 0)
+            .fold(0, |split, segment| split | match segment {
+                0 => 0b11,
+                3 => 0b11 << 4,
+                1 | 2 => 1 << (segment + 1),
+                _ => 1 << (segment + 2),
+            });
+        DisplayDefinition {
+            segments: 16,
+            glyphs: FOURTEEN_SEGMENT.iter().map(|(glyph, pattern)| (*glyph, split(*pattern))).collect(),
+        }
+    }
+
+    pub fn segments(&self) -> usize {
+        self.segments
+    }
+    pub fn glyphs(&self) -> &[(char, Pattern)] {
+        &self.glyphs
+    }
+    pub fn glyph_of(&self, pattern: Pattern) -> Option<char> {
+        self.glyphs.iter().find(|(_, glyph)| *glyph == pattern).map(|(glyph, _)| *glyph)
+    }
+
+    /// Segment counts shared by no two glyphs, such patterns are recognised without decoding
+    pub fn unique_sizes(&self) -> Vec<u32> {
+        let sizes: Vec<u32> = self.glyphs.iter().map(|(_, pattern)| pattern.count_ones()).collect();
+        sizes.iter().filter(|size| sizes.iter().filter(|other| other == size).count() == 1).copied().collect()
+    }
+
+    /// Segments of the display not in pattern
+    pub fn negate(&self, pattern: Pattern) -> Pattern {
+        !pattern & ((1u64 << self.segments) - 1) as Pattern
+    }
+
+    /// Patterns of a `"patterns | outputs"` entry, outputs last, together with output strings
+    fn parse_entry<'a>(&self, entry: &'a str) -> Result<(Vec<Pattern>, Vec<&'a str>), DecodeError> {
+        let (signals, outputs) = entry.split_once('|').ok_or(DecodeError::MissingSeparator)?;
+        let outputs: Vec<&str> = outputs.split_whitespace().collect();
+        let patterns = signals.split_whitespace().chain(outputs.iter().copied())
+            .map(|pattern| parse_pattern(pattern, self.segments))
+            .collect::<Result<Vec<Pattern>, DecodeError>>()?;
+        Ok((patterns, outputs))
+    }
+
+    fn read(&self, mapping: &WireMapping, patterns: &[Pattern], outputs: &[&str]) -> Result<String, DecodeError> {
+        outputs.iter().zip(&patterns[patterns.len() - outputs.len()..])
+            .map(|(output, pattern)| self.glyph_of(mapping.apply(*pattern))
+                .ok_or(DecodeError::UnknownOutput { pattern: output.to_string() }))
+            .collect()
+    }
+
+    /// Reads outputs of a `"patterns | outputs"` entry, the wiring has to be the only one fitting the patterns
+    pub fn decode(&self, entry: &str) -> Result<String, DecodeError> {
+        let (patterns, outputs) = self.parse_entry(entry)?;
+        let glyphs: Vec<Pattern> = self.glyphs.iter().map(|(_, pattern)| *pattern).collect();
+        let mapping = solve(&patterns, &glyphs, self.segments)?;
+        self.read(&mapping, &patterns, &outputs)
+    }
+
+    /// Reads outputs like `decode`, but fitting mappings may differ as long as they agree on the reading
+    pub fn decode_consensus(&self, entry: &str) -> Result<String, DecodeError> {
+        let (patterns, outputs) = self.parse_entry(entry)?;
+        let glyphs: Vec<Pattern> = self.glyphs.iter().map(|(_, pattern)| *pattern).collect();
+        let found = mappings(&patterns, &glyphs, self.segments, MAPPING_LIMIT);
+        if found.len() == MAPPING_LIMIT {
+            return Err(DecodeError::AmbiguousMapping);
+        }
+        let mut readings = found.iter().map(|mapping| self.read(mapping, &patterns, &outputs));
+        let reading = readings.next().ok_or(DecodeError::NoMapping)??;
+        match readings.all(|other| other.as_ref() == Ok(&reading)) {
+            true => Ok(reading),
+            false => Err(DecodeError::AmbiguousReading),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use crate::display::DisplayDefinition;
+    use crate::solver::{DecodeError, WireMapping};
+
+    #[test]
+    fn definitions() {
+        for display in [DisplayDefinition::seven_segment(), DisplayDefinition::fourteen_segment(), DisplayDefinition::sixteen_segment()] {
+            assert_eq!(DisplayDefinition::new(display.segments(), display.glyphs().to_vec()), Ok(display.clone()));
+        }
+        assert_eq!(DisplayDefinition::seven_segment().unique_sizes(), vec![2, 4, 3, 7]);
+        assert_eq!(DisplayDefinition::seven_segment().negate(0b1111011), 0b0000100);
+        assert!(DisplayDefinition::from_table(3, &[('x', "ab"), ('y', "ba")]).is_err());
+        assert!(DisplayDefinition::from_table(3, &[('x', "ad")]).is_err());
+        let full = DisplayDefinition::new(32, vec![('x', 0), ('y', 1 << 31)]).unwrap();
+        assert_eq!(full.negate(1 << 31), !(1 << 31));
+        assert!(DisplayDefinition::new(33, vec![('x', 0)]).is_err());
+    }
+
+    #[test]
+    fn consensus() {
+        let display = DisplayDefinition::seven_segment();
+        // Wires of `1` and `7` can be swapped among themselves, yet every such mapping reads `1`
+        assert_eq!(display.decode("ab dab eafb | ab"), Err(DecodeError::AmbiguousMapping));
+        assert_eq!(display.decode_consensus("ab dab eafb | ab"), Ok("1".to_string()));
+        assert_eq!(display.decode_consensus("ab dab | acdeg"), Err(DecodeError::AmbiguousReading));
+        assert_eq!(display.decode_consensus("ab ac | bc"), Err(DecodeError::NoMapping));
+    }
+
+    #[test]
+    fn unscrambles_other_displays() {
+        let fourteen = DisplayDefinition::fourteen_segment();
+        let rng = &mut StdRng::seed_from_u64(14);
+        let wiring = WireMapping((0..14).map(|wire| (wire * 5 + 3) % 14).collect());
+        assert_eq!(fourteen.decode(&fourteen.encode(&wiring, "HELLO2021", rng).unwrap()), Ok("HELLO2021".to_string()));
+        let sixteen = DisplayDefinition::sixteen_segment();
+        let wiring = WireMapping((0..16).rev().collect());
+        let entry = sixteen.encode(&wiring, "AOC", rng).unwrap();
+        // Halves of split bars always light together so their wires can be swapped
+        assert_eq!(sixteen.decode(&entry), Err(DecodeError::AmbiguousMapping));
+        assert_eq!(sixteen.decode_consensus(&entry), Ok("AOC".to_string()));
+        // Traffic light with three lamps
+        let lamps = DisplayDefinition::from_table(3, &[('R', "a"), ('Y', "ab"), ('G', "c")]).unwrap();
+        assert_eq!(lamps.decode("b ab c | b ab"), Ok("RY".to_string()));
+        assert_eq!(lamps.decode("| a"), Err(DecodeError::AmbiguousMapping));
+        assert_eq!(lamps.decode_consensus("| a"), Err(DecodeError::AmbiguousReading));
+        assert_eq!(WireMapping(vec![1, 0, 2]).apply(0b011), 0b011);
+    }
+}
@@ -0,0 +1,106 @@
+use rand::Rng;
+use rand::seq::SliceRandom;
+use crate::display::DisplayDefinition;
+use crate::solver::{DecodeError, Pattern, WireMapping};
+
+impl WireMapping {
+    /// Random wiring of display with `segments` segments
+    pub fn random<R: Rng>(segments: usize, rng: &mut R) -> Self {
+        let mut wiring: Vec<usize> = (0..segments).collect();
+        wiring.shuffle(rng);
+        WireMapping(wiring)
+    }
+
+    /// Wire letters driving lit segments, in random order
+    pub fn scramble<R: Rng>(&self, segments: Pattern, rng: &mut R) -> String {
+        let mut wires: Vec<char> = self.0.iter().enumerate()
+            .filter(|(_, segment)| segments & 1 << **segment != 0)
+            .map(|(wire, _)| (b'a' + wire as u8) as char)
+            .collect();
+        wires.shuffle(rng);
+        wires.into_iter().collect()
+    }
+}
+
+impl DisplayDefinition {
+    fn pattern_of(&self, glyph: char) -> Result<Pattern, DecodeError> {
+        self.glyphs().iter()
+            .find(|(other, _)| *other == glyph)
+            .map(|(_, pattern)| *pattern)
+            .ok_or(DecodeError::UnknownGlyph { glyph })
+    }
+
+    /// Inverse of `decode`, entry with every glyph once in random order followed by `reading`
+    pub fn encode<R: Rng>(&self, wiring: &WireMapping, reading: &str, rng: &mut R) -> Result<String, DecodeError> {
+        let mut segments = wiring.0.clone();
+        segments.sort_unstable();
+        if !segments.into_iter().eq(0..self.segments()) {
+            return Err(DecodeError::InvalidWiring { wires: wiring.0.len(), segments: self.segments() });
+        }
+        let mut signals: Vec<String> = self.glyphs().iter()
+            .map(|(_, pattern)| wiring.scramble(*pattern, rng))
+            .collect();
+        signals.shuffle(rng);
+        let outputs = reading.chars()
+            .map(|glyph| self.pattern_of(glyph).map(|pattern| wiring.scramble(pattern, rng)))
+            .collect::<Result<Vec<String>, DecodeError>>()?;
+        Ok(format!("{} | {}", signals.join(" "), outputs.join(" ")))
+    }
+}
+
+/// Digits of a puzzle entry
+const DIGITS: usize = 4;
+
+/// Puzzle entry showing `number` on four seven segment digits
+pub fn encode_number<R: Rng>(wiring: &WireMapping, number: usize, rng: &mut R) -> Result<String, DecodeError> {
+    if number >= 10usize.pow(DIGITS as u32) {
+        return Err(DecodeError::NumberOutOfRange { number });
+    }
+    DisplayDefinition::seven_segment().encode(wiring, &format!("{:0width$}", number, width = DIGITS), rng)
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+    use crate::decode_numbers;
+    use crate::display::DisplayDefinition;
+    use crate::encoder::encode_number;
+    use crate::solver::{DecodeError, WireMapping};
+
+    #[test]
+    fn encodes_example() {
+        // Wiring of the puzzle example, wire 'd' drives the top segment
+        let wiring = WireMapping(vec![2, 5, 6, 0, 1, 3, 4]);
+        let entry = encode_number(&wiring, 5353, &mut StdRng::seed_from_u64(1)).unwrap();
+        let outputs: Vec<String> = entry.split_once(" | ").unwrap().1.split_whitespace()
+            .map(|output| { let mut wires: Vec<char> = output.chars().collect(); wires.sort(); wires.into_iter().collect() })
+            .collect();
+        assert_eq!(outputs, vec!["bcdef", "abcdf", "bcdef", "abcdf"]);
+        assert_eq!(entry.split_once(" | ").unwrap().0.split_whitespace().count(), 10);
+        assert_eq!(DisplayDefinition::seven_segment().encode(&wiring, "12a", &mut StdRng::seed_from_u64(1)),
+                   Err(DecodeError::UnknownGlyph { glyph: 'a' }));
+        assert_eq!(encode_number(&wiring, 10_000, &mut StdRng::seed_from_u64(1)), Err(DecodeError::NumberOutOfRange { number: 10_000 }));
+        assert!(encode_number(&wiring, 9_999, &mut StdRng::seed_from_u64(1)).is_ok());
+        assert_eq!(encode_number(&WireMapping(vec![0, 1, 2]), 1, &mut StdRng::seed_from_u64(1)),
+                   Err(DecodeError::InvalidWiring { wires: 3, segments: 7 }));
+        assert_eq!(encode_number(&WireMapping(vec![0, 0, 1, 2, 3, 4, 5]), 1, &mut StdRng::seed_from_u64(1)),
+                   Err(DecodeError::InvalidWiring { wires: 7, segments: 7 }));
+    }
+
+    #[test]
+    fn roundtrip() {
+        let mut rng = StdRng::seed_from_u64(2021);
+        for _ in 0..200 {
+            let wiring = WireMapping::random(7, &mut rng);
+            let number = rng.gen_range(0..10_000);
+            assert_eq!(decode_numbers(&encode_number(&wiring, number, &mut rng).unwrap()), Ok(number));
+        }
+        let fourteen = DisplayDefinition::fourteen_segment();
+        for _ in 0..20 {
+            let wiring = WireMapping::random(14, &mut rng);
+            let text: String = (0..6).map(|_| fourteen.glyphs()[rng.gen_range(0..36)].0).collect();
+            assert_eq!(fourteen.decode(&fourteen.encode(&wiring, &text, &mut rng).unwrap()), Ok(text));
+        }
+    }
+}
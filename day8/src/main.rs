@@ -1,130 +1,104 @@
-use std::collections::HashMap;
 use std::fs;
+use std::num::IntErrorKind;
 use std::str::Lines;
-use itertools::Itertools;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use crate::display::DisplayDefinition;
+use crate::encoder::encode_number;
+use crate::solver::{DecodeError, WireMapping};
+
+mod solver;
+mod display;
+mod encoder;
+mod diagnostics;
 
 
 fn get_part_1(lines: Lines) -> usize
 {
+    let unique_sizes = DisplayDefinition::seven_segment().unique_sizes();
     lines.map(|line| line.split_once("|").unwrap().1
-        .split_whitespace().into_iter()
-        .map(|active_wires| active_wires.trim().len())
-        .filter(|len| vec![2, 4, 3, 7].contains(len))
+        .split_whitespace()
+        .filter(|active_wires| unique_sizes.contains(&(active_wires.trim().len() as u32)))
         .count())
         .sum()
 }
 
-fn find_simple_number(codes: &mut HashMap<u8, String>, number_codes: &mut Vec<String>, number: u8, used_segemnts: usize) {
-    codes.insert(number, number_codes.iter().find(|code| code.len() == used_segemnts).unwrap().to_string());
-    number_codes.retain(|code| code != codes.get(&number).unwrap());
+fn decode_numbers(entry: &str) -> Result<usize, DecodeError> {
+    let reading = DisplayDefinition::seven_segment().decode(entry)?;
+    reading.parse().map_err(|error: std::num::ParseIntError| match error.kind() {
+        IntErrorKind::Empty => DecodeError::EmptyReading,
+        IntErrorKind::PosOverflow => DecodeError::ReadingOverflow { reading: reading.clone() },
+        _ => DecodeError::NotANumber { reading: reading.clone() },
+    })
 }
 
-fn negate_segments(str: &str) -> String {
-    let mut chars: Vec<char> = "abcdefg".chars().into_iter().collect();
-    chars.retain(|c| !str.chars().any(|original| original == *c));
-    chars.into_iter().collect()
+/// Entries whose outputs alone are enough to read them, whatever the wiring
+fn readable_from_outputs(lines: Lines) -> usize {
+    let display = DisplayDefinition::seven_segment();
+    lines.filter_map(|line| line.split_once('|'))
+        .filter(|(_, outputs)| display.decode_consensus(&format!("|{}", outputs)).is_ok())
+        .count()
 }
 
-fn decode_numbers(entry: &str) -> usize {
-    let mut number_codes: Vec<String> = entry.replace('|', "").split_whitespace()
-        .into_iter().map(|str| String::from(str.chars().sorted().rev().collect::<String>())).collect();
-    let mut codes: HashMap<u8, String> = HashMap::new();
-    // Look for 1
-    find_simple_number(&mut codes, &mut number_codes, 1, 2);
-    // Look for 7
-    find_simple_number(&mut codes, &mut number_codes, 7, 3);
-    // Look for 8
-    find_simple_number(&mut codes, &mut number_codes, 8, 7);
-    // Look for 4
-    find_simple_number(&mut codes, &mut number_codes, 4, 4);
-    // Look for 6 (6 is number that has a missing segment from 1)
-    {
-        let six_code = number_codes.as_slice().iter()
-            .filter(|code| code.len() == 6)
-            .find(|code| negate_segments(code).chars()
-                .all(|c| codes.get(&1u8).unwrap().contains(c))).unwrap();
-        codes.insert(6, six_code.to_string());
-    }
-    number_codes.retain(|code| code != codes.get(&6u8).unwrap());
-    // Look for '9' ('9' is number with 6 segments and contains all '4' segments)
-    {
-        let nine_code = number_codes.as_slice().iter()
-            .filter(|code| code.len() == 6)
-            .find(|code|
-                codes.get(&4u8).unwrap().chars().all(|four_segemnt|
-                    code.contains(four_segemnt)
-                )
-            )
-            .unwrap();
-        codes.insert(9, nine_code.to_string());
-    }
-    number_codes.retain(|code| code != codes.get(&9u8).unwrap());
-    // Look for '9' ('9' is number with 6 segments and contains all '4' segments)
-    {
-        let zero_code = number_codes.as_slice().iter()
-            .find(|code| code.len() == 6)
-            .unwrap();
-        codes.insert(0, zero_code.to_string());
-    }
-    number_codes.retain(|code| code != codes.get(&0u8).unwrap());
-    // Look for '5' ('5' is number with 5 segments and i missing the same segemnts as '6' and '9'
-    {
-        let five_code = number_codes.as_slice().iter()
-            .filter(|code| code.len() == 5)
-            .filter(|code| code.chars().all(|c|
-                !negate_segments(codes.get(&6u8).unwrap())
-                    .chars()
-                    .any(|six_missing| six_missing == c)
-            ))
-            .find(|code| code.chars().all(|c|
-                !negate_segments(codes.get(&9u8).unwrap())
-                    .chars()
-                    .any(|nine_missing| nine_missing == c)
-            ))
-            .unwrap();
-        codes.insert(5, five_code.to_string());
-    }
-    number_codes.retain(|code| code != codes.get(&5u8).unwrap());
-    // Look for '3' ('3' is number with 5 segments and contains '1' segments
-    {
-        let three_code = number_codes.as_slice().iter()
-            .filter(|code| code.len() == 5)
-            .find(|code|
-                // code.contains(codes.get(&1u8).unwrap())
-                codes.get(&1u8).unwrap().chars().all(
-                    |c|code.contains(c)
-                )
-            )
-            .unwrap();
-        codes.insert(3, three_code.to_string());
-    }
-    number_codes.retain(|code| code != codes.get(&3u8).unwrap());
-    // Look for '2'
-    codes.insert(2, number_codes[0].to_string());
-    number_codes.retain(|code| code != codes.get(&2u8).unwrap());
-    println!("{:?}\n{:?}", codes, number_codes);
-    // Convert output
-    entry.split_once('|').unwrap().1.split_whitespace().into_iter()
-        .map(|code| code.chars().sorted().rev().collect::<String>())
-        .map(|code| codes.iter()
-            .find(|(_,val)| val.as_str() == code).unwrap())
-        .map(|(key,_)|key.to_string())
-        .join("").parse().unwrap()
+fn get_part_2(lines: Lines) -> Result<usize, DecodeError> {
+    lines.map(decode_numbers).sum()
 }
 
-fn get_part_2(lines: Lines) -> usize {
-    lines.map(|line| decode_numbers(line)).sum()
+/// `seven`, `fourteen`, `sixteen` or a file with a `glyph segments` pair per line, segments as letters
+fn display_from_arg(arg: &str) -> Result<DisplayDefinition, DecodeError> {
+    match arg {
+        "seven" => Ok(DisplayDefinition::seven_segment()),
+        "fourteen" => Ok(DisplayDefinition::fourteen_segment()),
+        "sixteen" => Ok(DisplayDefinition::sixteen_segment()),
+        path => {
+            let table = fs::read_to_string(path)
+                .map_err(|error| DecodeError::InvalidDisplay { reason: error.to_string() })?;
+            let table = table.lines()
+                .filter_map(|line| line.trim().split_once(' '))
+                .map(|(glyph, segments)| match glyph.chars().next() {
+                    Some(glyph) => Ok((glyph, segments.trim())),
+                    None => Err(DecodeError::InvalidDisplay { reason: format!("no glyph for {}", segments) }),
+                })
+                .collect::<Result<Vec<(char, &str)>, DecodeError>>()?;
+            let segments = table.iter().flat_map(|(_, segments)| segments.bytes())
+                .map(|segment| segment.saturating_sub(b'a') as usize + 1)
+                .max().unwrap_or(0);
+            DisplayDefinition::from_table(segments, &table)
+        }
+    }
 }
 
 fn main() {
     let string = fs::read_to_string("day8/input_data.dat").unwrap();
     println!("Result part 1: {}", get_part_1(string.as_str().lines()));
-    println!("Result part 2: {}", get_part_2(string.as_str().lines()));
+    println!("Result part 2: {}", get_part_2(string.as_str().lines()).unwrap());
+    println!("Entries readable from outputs alone: {}", readable_from_outputs(string.as_str().lines()));
+    let rng = &mut StdRng::seed_from_u64(2021);
+    let entry = encode_number(&WireMapping::random(7, rng), 2021, rng).unwrap();
+    println!("Scrambled 2021: {}", entry);
+    println!("Decoded back: {}", decode_numbers(&entry).unwrap());
+    // Same entry with wire `a` dead
+    let broken: String = entry.chars().filter(|wire| *wire != 'a').collect();
+    let seven = DisplayDefinition::seven_segment();
+    let diagnostics = seven.diagnose(&broken, 1).unwrap();
+    let suspected = diagnostics.suspected_stuck_on() | diagnostics.suspected_stuck_off();
+    println!("With dead wire: {} (certain: {}) explained by {} fault(s), stuck on: {:07b}, stuck off: {:07b}, healthy: {:07b}",
+             diagnostics.reading(), diagnostics.is_certain(), diagnostics.diagnoses[0].fault.count(),
+             diagnostics.suspected_stuck_on(), diagnostics.suspected_stuck_off(), seven.negate(suspected));
+    // Other hardware: `day8 <display> <text>`
+    let mut args = std::env::args().skip(1);
+    let display = display_from_arg(&args.next().unwrap_or_else(|| "fourteen".to_string())).unwrap();
+    let text = args.next().unwrap_or_else(|| "AOC2021".to_string());
+    let entry = display.encode(&WireMapping::random(display.segments(), rng), &text, rng).unwrap();
+    println!("Scrambled {}: {}", text, entry);
+    println!("Decoded back: {}", display.decode_consensus(&entry).unwrap());
 }
 
 #[cfg(test)]
 mod test {
-    use crate::{get_part_1, get_part_2, negate_segments};
+    use crate::{decode_numbers, get_part_1, get_part_2};
+    use crate::display::DisplayDefinition;
+    use crate::solver::{DecodeError, parse_pattern};
 
     static INPUT: &str = r#"be cfbegad cbdgef fgaecd cgeb fdcge agebfd fecdb fabcd edb | fdgacbe cefdb cefbgd gcbe
 edbfga begcd cbg gc gcadebf fbgde acbgfd abcde gfcbed gfec | fcgedb cgb dgebacf gc
@@ -139,13 +113,27 @@ gcafb gcf dcaebfg ecagb gf abcdeg gaef cafbge fdbac fegbdc | fgae cfgab fg bagce
 
     #[test]
     fn tools() {
-        assert_eq!(negate_segments("cdfgeb"), "a".to_string());
+        let display = DisplayDefinition::seven_segment();
+        assert_eq!(display.negate(parse_pattern("cdfgeb", 7).unwrap()), parse_pattern("a", 7).unwrap());
     }
 
     #[test]
     fn test_data() {
         assert_eq!(get_part_1(INPUT.lines()), 26);
-        assert_eq!(get_part_2("acedgfb cdfbe gcdfa fbcad dab cefabd cdfgeb eafb cagedb ab | cdfeb fcadb cdfeb cdbaf".lines()), 5353);
-        assert_eq!(get_part_2(INPUT.lines()), 61229);
+        assert_eq!(get_part_2("acedgfb cdfbe gcdfa fbcad dab cefabd cdfgeb eafb cagedb ab | cdfeb fcadb cdfeb cdbaf".lines()), Ok(5353));
+        assert_eq!(get_part_2(INPUT.lines()), Ok(61229));
+    }
+
+    #[test]
+    fn corrupted_entries() {
+        assert_eq!(decode_numbers("ab dab eafb"), Err(DecodeError::MissingSeparator));
+        assert_eq!(decode_numbers("ab dab eafb | ab"), Err(DecodeError::AmbiguousMapping));
+        assert_eq!(decode_numbers("ab abc abcd | abcdx"), Err(DecodeError::InvalidPattern { pattern: "abcdx".to_string() }));
+        assert_eq!(decode_numbers("ab ac | bc"), Err(DecodeError::NoMapping));
+        let signals = "acedgfb cdfbe gcdfa fbcad dab cefabd cdfgeb eafb cagedb ab";
+        assert_eq!(decode_numbers(&format!("{} |", signals)), Err(DecodeError::EmptyReading));
+        let outputs = vec!["ab"; 21].join(" ");
+        assert_eq!(decode_numbers(&format!("{} | {}", signals, outputs)),
+                   Err(DecodeError::ReadingOverflow { reading: "1".repeat(21) }));
     }
 }
\ No newline at end of file
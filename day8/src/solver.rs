@@ -0,0 +1,150 @@
+use custom_error::custom_error;
+
+/// Lit segments or active wires, bit `i` stands for letter `'a' + i`
+pub type Pattern = u32;
+
+custom_error! {
+    #[derive(PartialEq)]
+    pub DecodeError
+    InvalidPattern{pattern: String} = "Pattern {pattern} uses unknown wires",
+    MissingSeparator = "Entry has no '|' separator",
+    NoMapping = "No wire mapping fits the patterns",
+    AmbiguousMapping = "More than one wire mapping fits the patterns",
+    AmbiguousReading = "Wire mappings that fit the patterns disagree on the outputs",
+    UnknownOutput{pattern: String} = "Output {pattern} is not a glyph of the display",
+    UnknownGlyph{glyph: char} = "Display cannot show {glyph}",
+    InvalidDisplay{reason: String} = "Invalid display definition: {reason}",
+    EmptyReading = "Entry has no outputs to read",
+    ReadingOverflow{reading: String} = "Reading {reading} does not fit in a number",
+    NotANumber{reading: String} = "Reading {reading} is not a number",
+    InvalidWiring{wires: usize, segments: usize} = "Wiring of {wires} wires is not a permutation of {segments} segments",
+    NumberOutOfRange{number: usize} = "{number} does not fit on the display",
+}
+
+pub fn parse_pattern(string: &str, wires: usize) -> Result<Pattern, DecodeError> {
+    string.chars().try_fold(0, |pattern, wire| match (wire as u32).checked_sub('a' as u32) {
+        Some(bit) if (bit as usize) < wires => Ok(pattern | 1 << bit),
+        _ => Err(DecodeError::InvalidPattern { pattern: string.to_string() }),
+    })
+}
+
+/// Segment every wire is connected to
+#[derive(Debug, Clone, PartialEq)]
+pub struct WireMapping(pub Vec<usize>);
+
+impl WireMapping {
+    /// Segments lit by active wires
+    pub fn apply(&self, pattern: Pattern) -> Pattern {
+        self.0.iter().enumerate()
+            .filter(|(wire, _)| pattern & 1 << wire != 0)
+            .fold(0, |segments, (_, segment)| segments | 1 << segment)
+    }
+}
+
+/// Backtracking search over wire assignments, every pattern has to stay explainable by some glyph
+struct Search<'a> {
+    patterns: &'a [Pattern],
+    glyphs: &'a [Pattern],
+    /// Segments each wire may still be connected to
+    candidates: Vec<Pattern>,
+    assigned: Vec<Option<usize>>,
+    limit: usize,
+    found: Vec<WireMapping>,
+}
+
+impl Search<'_> {
+    /// Some glyph with the pattern's segment count lights exactly the images of assigned active wires
+    fn consistent(&self, pattern: Pattern) -> bool {
+        let (mut lit, mut dark) = (0, 0);
+        self.assigned.iter().enumerate().for_each(|(wire, segment)| match segment {
+            Some(segment) if pattern & 1 << wire != 0 => lit |= 1 << segment,
+            Some(segment) => dark |= 1 << segment,
+            None => {}
+        });
+        self.glyphs.iter().any(|glyph| glyph.count_ones() == pattern.count_ones() && glyph & lit == lit && glyph & dark == 0)
+    }
+
+    fn run(&mut self, used: Pattern) {
+        if self.found.len() >= self.limit {
+            return;
+        }
+        let next = (0..self.assigned.len())
+            .filter(|wire| self.assigned[*wire].is_none())
+            .min_by_key(|wire| (self.candidates[*wire] & !used).count_ones());
+        let wire = match next {
+            None => {
+                self.found.push(WireMapping(self.assigned.iter().map(|segment| segment.unwrap()).collect()));
+                return;
+            }
+            Some(wire) => wire,
+        };
+        let options = self.candidates[wire] & !used;
+        for segment in (0..Pattern::BITS as usize).filter(|segment| options & 1 << segment != 0) {
+            self.assigned[wire] = Some(segment);
+            if self.patterns.iter().all(|pattern| self.consistent(*pattern)) {
+                self.run(used | 1 << segment);
+            }
+        }
+        self.assigned[wire] = None;
+    }
+}
+
+/// Up to `limit` mappings of `wires` wires to segments under which every pattern is a glyph
+pub fn mappings(patterns: &[Pattern], glyphs: &[Pattern], wires: usize, limit: usize) -> Vec<WireMapping> {
+    let all: Pattern = if wires >= Pattern::BITS as usize { Pattern::MAX } else { (1 << wires) - 1 };
+    let mut candidates = vec![all; wires];
+    // Glyphs of matching size bound segments of active and inactive wires
+    for pattern in patterns {
+        let sized = glyphs.iter().filter(|glyph| glyph.count_ones() == pattern.count_ones());
+        let (union, intersection) = sized.fold((0, all), |(union, intersection), glyph| (union | glyph, intersection & glyph));
+        for (wire, candidate) in candidates.iter_mut().enumerate() {
+            *candidate &= if pattern & 1 << wire != 0 { union } else { !intersection };
+        }
+    }
+    let mut search = Search { patterns, glyphs, candidates, assigned: vec![None; wires], limit, found: vec![] };
+    search.run(0);
+    search.found
+}
+
+/// The only mapping consistent with patterns
+pub fn solve(patterns: &[Pattern], glyphs: &[Pattern], wires: usize) -> Result<WireMapping, DecodeError> {
+    let mut found = mappings(patterns, glyphs, wires, 2);
+    match found.len() {
+        0 => Err(DecodeError::NoMapping),
+        1 => Ok(found.remove(0)),
+        _ => Err(DecodeError::AmbiguousMapping),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::display::DisplayDefinition;
+    use crate::solver::{DecodeError, mappings, parse_pattern, Pattern, solve, WireMapping};
+
+    fn digits() -> Vec<Pattern> {
+        DisplayDefinition::seven_segment().glyphs().iter().map(|(_, pattern)| *pattern).collect()
+    }
+
+    fn patterns(string: &str) -> Vec<Pattern> {
+        string.split_whitespace().map(|pattern| parse_pattern(pattern, 7).unwrap()).collect()
+    }
+
+    #[test]
+    fn solves_example() {
+        let signals = patterns("acedgfb cdfbe gcdfa fbcad dab cefabd cdfgeb eafb cagedb ab");
+        // Wire 'd' drives top segment 'a', 'e' top left 'b' and so on
+        let mapping = solve(&signals, &digits(), 7).unwrap();
+        assert_eq!(mapping, WireMapping(vec![2, 5, 6, 0, 1, 3, 4]));
+        assert_eq!(mapping.apply(parse_pattern("ab", 7).unwrap()), digits()[1]);
+        assert_eq!(parse_pattern("az", 7), Err(DecodeError::InvalidPattern { pattern: "az".to_string() }));
+    }
+
+    #[test]
+    fn typed_errors() {
+        // Only `1` and `7` leave wires b-g interchangeable in many ways
+        assert_eq!(solve(&patterns("ab dab"), &digits(), 7), Err(DecodeError::AmbiguousMapping));
+        assert_eq!(mappings(&patterns("ab dab"), &digits(), 7, 1000).len(), 48);
+        assert_eq!(solve(&patterns("ab abc abcdefg abcd ad"), &digits(), 7), Err(DecodeError::NoMapping));
+        assert_eq!(solve(&[0b11111], &[0b11], 5), Err(DecodeError::NoMapping));
+    }
+}
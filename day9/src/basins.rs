@@ -0,0 +1,249 @@
+use ndarray::Array2;
+use crate::rules::{BasinRules, Partition};
+
+/// Disjoint sets of cells with path halving and union by size
+pub(crate) struct DisjointSet {
+    parent: Vec<usize>,
+    size: Vec<usize>,
+}
+
+impl DisjointSet {
+    pub(crate) fn new(len: usize) -> Self {
+        DisjointSet { parent: (0..len).collect(), size: vec![1; len] }
+    }
+
+    pub(crate) fn find(&mut self, mut idx: usize) -> usize {
+        while self.parent[idx] != idx {
+            self.parent[idx] = self.parent[self.parent[idx]];
+            idx = self.parent[idx];
+        }
+        idx
+    }
+
+    pub(crate) fn union(&mut self, lhs: usize, rhs: usize) {
+        let (lhs, rhs) = (self.find(lhs), self.find(rhs));
+        if lhs == rhs {
+            return;
+        }
+        let (big, small) = if self.size[lhs] >= self.size[rhs] { (lhs, rhs) } else { (rhs, lhs) };
+        self.parent[small] = big;
+        self.size[big] += self.size[small];
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Basin {
+    /// Lowest cell, the first one in row order when several are equally low
+    pub low_point: (usize, usize),
+    /// Cells in row order
+    pub cells: Vec<(usize, usize)>,
+}
+
+impl Basin {
+    pub fn size(&self) -> usize {
+        self.cells.len()
+    }
+}
+
+/// Basin label of every cell, walls have none
+#[derive(Debug, Clone, PartialEq)]
+pub struct BasinMap {
+    pub labels: Array2<Option<usize>>,
+    /// Basins indexed by label, labels follow row order of the first cell
+    pub basins: Vec<Basin>,
+}
+
+impl BasinMap {
+    #[cfg(test)]
+    pub fn basin_at(&self, cell: (usize, usize)) -> Option<&Basin> {
+        self.labels.get(cell).copied().flatten().map(|label| &self.basins[label])
+    }
+
+    /// Sizes of all basins, largest first
+    pub fn sizes(&self) -> Vec<usize> {
+        let mut sizes: Vec<usize> = self.basins.iter().map(Basin::size).collect();
+        sizes.sort_unstable_by(|lhs, rhs| rhs.cmp(lhs));
+        sizes
+    }
+}
+
+/// Labels puzzle basins, see `BasinRules::default`
+pub fn label_basins(arr: &Array2<u8>) -> BasinMap {
+    label_basins_with(arr, &BasinRules::default())
+}
+
+/// Labels basins in a single union-find pass followed by one labelling pass
+pub fn label_basins_with(arr: &Array2<u8>, rules: &BasinRules) -> BasinMap {
+    let (rows, cols) = arr.dim();
+    let mut sets = match rules.partition {
+        Partition::Walls => connected(arr, rules),
+        Partition::Downhill => downhill(arr, rules),
+    };
+    let mut label_of_root = vec![None; rows * cols];
+    let mut labels = Array2::from_elem((rows, cols), None);
+    let mut basins: Vec<Basin> = vec![];
+    for ((row, col), height) in arr.indexed_iter() {
+        if rules.is_wall(*height) {
+            continue;
+        }
+        let root = sets.find(row * cols + col);
+        let label = *label_of_root[root].get_or_insert_with(|| {
+            basins.push(Basin { low_point: (row, col), cells: vec![] });
+            basins.len() - 1
+        });
+        let basin = &mut basins[label];
+        if *height < arr[basin.low_point] {
+            basin.low_point = (row, col);
+        }
+        basin.cells.push((row, col));
+        labels[[row, col]] = Some(label);
+    }
+    BasinMap { labels, basins }
+}
+
+/// Joins every cell with its neighbours that are not walls
+fn connected(arr: &Array2<u8>, rules: &BasinRules) -> DisjointSet {
+    let (rows, cols) = arr.dim();
+    let mut sets = DisjointSet::new(rows * cols);
+    for ((row, col), height) in arr.indexed_iter() {
+        if rules.is_wall(*height) {
+            continue;
+        }
+        rules.connectivity.neighbours((row, col), arr.dim())
+            .filter(|neighbour| !rules.is_wall(arr[*neighbour]))
+            .for_each(|(r, c)| sets.union(row * cols + col, r * cols + c));
+    }
+    sets
+}
+
+/// Groups neighbouring cells of equal height into plateaus
+fn plateaus(arr: &Array2<u8>, rules: &BasinRules) -> DisjointSet {
+    let (rows, cols) = arr.dim();
+    let mut sets = DisjointSet::new(rows * cols);
+    for ((row, col), height) in arr.indexed_iter() {
+        rules.connectivity.neighbours((row, col), arr.dim())
+            .filter(|neighbour| arr[*neighbour] == *height)
+            .for_each(|(r, c)| sets.union(row * cols + col, r * cols + c));
+    }
+    sets
+}
+
+/// Lowest cell next to every plateau that is lower than it, None for low regions
+fn plateau_exits(arr: &Array2<u8>, rules: &BasinRules, plateaus: &mut DisjointSet) -> Vec<Option<(usize, usize)>> {
+    let cols = arr.dim().1;
+    let mut exits: Vec<Option<(usize, usize)>> = vec![None; arr.len()];
+    for ((row, col), height) in arr.indexed_iter() {
+        let root = plateaus.find(row * cols + col);
+        for neighbour in rules.connectivity.neighbours((row, col), arr.dim()) {
+            if arr[neighbour] < *height && exits[root].is_none_or(|exit| arr[neighbour] < arr[exit]) {
+                exits[root] = Some(neighbour);
+            }
+        }
+    }
+    exits
+}
+
+/// Plateaus with no lower neighbour, single cells are ordinary low points
+pub fn low_regions(arr: &Array2<u8>, rules: &BasinRules) -> Vec<Vec<(usize, usize)>> {
+    let cols = arr.dim().1;
+    let mut sets = plateaus(arr, rules);
+    let exits = plateau_exits(arr, rules, &mut sets);
+    let mut region_of_root = vec![None; arr.len()];
+    let mut regions: Vec<Vec<(usize, usize)>> = vec![];
+    for ((row, col), height) in arr.indexed_iter() {
+        let root = sets.find(row * cols + col);
+        if exits[root].is_some() || rules.is_wall(*height) {
+            continue;
+        }
+        let region = *region_of_root[root].get_or_insert_with(|| {
+            regions.push(vec![]);
+            regions.len() - 1
+        });
+        regions[region].push((row, col));
+    }
+    regions
+}
+
+/// Every plateau drains through its lowest outside neighbour, following drains ends in a low region
+fn downhill(arr: &Array2<u8>, rules: &BasinRules) -> DisjointSet {
+    let cols = arr.dim().1;
+    let mut sets = plateaus(arr, rules);
+    let exits = plateau_exits(arr, rules, &mut sets);
+    let mut basins = DisjointSet::new(arr.len());
+    for ((row, col), _) in arr.indexed_iter() {
+        let idx = row * cols + col;
+        basins.union(idx, sets.find(idx));
+        if let Some((r, c)) = exits[sets.find(idx)] {
+            basins.union(idx, r * cols + c);
+        }
+    }
+    basins
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::{arr2, Array2};
+    use crate::basins::{label_basins, label_basins_with, low_regions};
+    use crate::rules::{BasinRules, Connectivity, Partition};
+    use crate::parse;
+    use crate::tests::TEST_DATA;
+
+    #[test]
+    fn labels_test_data() {
+        let map = label_basins(&parse(TEST_DATA.lines()));
+        assert_eq!(map.basins.len(), 4);
+        assert_eq!(map.sizes(), vec![14, 9, 9, 3]);
+        assert_eq!(map.basin_at((0, 0)).unwrap().low_point, (0, 1));
+        assert_eq!(map.basin_at((0, 8)).unwrap().low_point, (0, 9));
+        assert_eq!(map.basin_at((3, 3)).unwrap().low_point, (2, 2));
+        assert_eq!(map.basin_at((0, 2)), None);
+        assert_eq!(map.labels[[4, 9]], map.labels[[4, 5]]);
+        assert_ne!(map.labels[[4, 9]], map.labels[[2, 9]]);
+    }
+
+    #[test]
+    fn large_heightmap() {
+        // Walls every tenth row and column leave 9x9 basins
+        let size = 1000;
+        let arr = Array2::from_shape_fn((size, size), |(row, col)| if row % 10 == 9 || col % 10 == 9 { 9 } else { ((row * 7 + col * 3) % 9) as u8 });
+        let map = label_basins(&arr);
+        assert_eq!(map.basins.len(), 100 * 100);
+        assert!(map.basins.iter().all(|basin| basin.size() == 81));
+    }
+
+    #[test]
+    fn configurable_rules() {
+        let arr = parse(TEST_DATA.lines());
+        let eight = BasinRules { connectivity: Connectivity::Eight, ..BasinRules::default() };
+        // Basins touch diagonally everywhere
+        assert_eq!(label_basins_with(&arr, &eight).sizes(), vec![35]);
+        let low_walls = BasinRules { wall: Some(8), ..BasinRules::default() };
+        assert_eq!(label_basins_with(&arr, &low_walls).basin_at((3, 3)).unwrap().size(), 7);
+        let downhill = BasinRules::new(Connectivity::Four, None, Partition::Downhill);
+        let map = label_basins_with(&arr, &downhill);
+        assert_eq!(map.basins.len(), 4);
+        assert_eq!(map.sizes().iter().sum::<usize>(), 50);
+        assert_eq!(map.basin_at((1, 0)).unwrap().low_point, (0, 1));
+        assert_eq!("no-wall,downhill".parse(), Ok(downhill));
+        assert_eq!("eight".parse(), Ok(eight));
+        assert_eq!("wall:8".parse(), Ok(low_walls));
+        assert_eq!("uphill".parse::<BasinRules>(), Err(()));
+    }
+
+    #[test]
+    fn plateaus() {
+        let arr = arr2(&[
+            [3, 1, 1, 4],
+            [4, 1, 5, 2],
+            [6, 6, 6, 6],
+        ]);
+        let rules = BasinRules::new(Connectivity::Four, None, Partition::Downhill);
+        assert_eq!(low_regions(&arr, &rules), vec![vec![(0, 1), (0, 2), (1, 1)], vec![(1, 3)]]);
+        let map = label_basins_with(&arr, &rules);
+        assert_eq!(map.sizes(), vec![11, 1]);
+        // The whole bottom plateau drains through its lowest neighbour, even where it touches the other region
+        assert_eq!(map.labels[[2, 3]], map.labels[[1, 1]]);
+        assert_eq!(map.basin_at((0, 3)).unwrap().low_point, (0, 1));
+        assert_eq!(low_regions(&arr2(&[[5]]), &rules), vec![vec![(0, 0)]]);
+    }
+}
@@ -0,0 +1,272 @@
+use std::collections::VecDeque;
+use ndarray::Array2;
+use crate::basins::low_regions;
+use crate::rules::BasinRules;
+
+/// Neighbour every cell drains to, low regions do not drain anywhere.
+///
+/// Cells drain to their lowest lower neighbour, flat cells towards the closest cell of their plateau that has one.
+/// Only connectivity of the rules is used, water flows over walls too.
+pub fn drainage(arr: &Array2<u8>, rules: &BasinRules) -> Array2<Option<(usize, usize)>> {
+    let mut drains = Array2::from_elem(arr.dim(), None);
+    let mut queue = VecDeque::new();
+    for (cell, height) in arr.indexed_iter() {
+        let lowest = rules.connectivity.neighbours(cell, arr.dim())
+            .filter(|neighbour| arr[*neighbour] < *height)
+            .min_by_key(|neighbour| arr[*neighbour]);
+        if lowest.is_some() {
+            drains[cell] = lowest;
+            queue.push_back(cell);
+        }
+    }
+    while let Some(cell) = queue.pop_front() {
+        for neighbour in rules.connectivity.neighbours(cell, arr.dim()) {
+            if arr[neighbour] == arr[cell] && drains[neighbour].is_none() {
+                drains[neighbour] = Some(cell);
+                queue.push_back(neighbour);
+            }
+        }
+    }
+    drains
+}
+
+/// Low region together with all cells whose water ends up in it
+#[derive(Debug, Clone, PartialEq)]
+pub struct Catchment {
+    pub low_region: Vec<(usize, usize)>,
+    pub cells: Vec<(usize, usize)>,
+}
+
+impl Catchment {
+    pub fn area(&self) -> usize {
+        self.cells.len()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Catchments {
+    /// Index of catchment every cell belongs to
+    pub labels: Array2<usize>,
+    pub catchments: Vec<Catchment>,
+}
+
+/// Unlike downhill basins, cells of one plateau may drain to different low regions
+pub fn catchments(arr: &Array2<u8>, rules: &BasinRules) -> Catchments {
+    let flow_rules = BasinRules { wall: None, ..*rules };
+    let drains = drainage(arr, &flow_rules);
+    let regions = low_regions(arr, &flow_rules);
+    let mut labels: Array2<Option<usize>> = Array2::from_elem(arr.dim(), None);
+    regions.iter().enumerate()
+        .for_each(|(label, region)| region.iter().for_each(|cell| labels[*cell] = Some(label)));
+    let mut catchments: Vec<Catchment> = regions.into_iter()
+        .map(|low_region| Catchment { low_region, cells: vec![] })
+        .collect();
+    for (cell, _) in arr.indexed_iter() {
+        let mut path = vec![];
+        let mut current = cell;
+        while labels[current].is_none() {
+            path.push(current);
+            current = drains[current].unwrap();
+        }
+        let label = labels[current];
+        path.into_iter().for_each(|visited| labels[visited] = label);
+    }
+    let labels = labels.map(|label| label.unwrap());
+    labels.indexed_iter().for_each(|(cell, label)| catchments[*label].cells.push(cell));
+    Catchments { labels, catchments }
+}
+
+/// Catchments whose water forms one surface
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lake {
+    pub catchments: Vec<usize>,
+    pub volume: f64,
+    pub level: f64,
+    /// Cells lower than the water level
+    pub flooded: Vec<(usize, usize)>,
+}
+
+/// Water spilling over a saddle into a neighbouring catchment
+#[derive(Debug, Clone, PartialEq)]
+pub struct Overflow {
+    pub from: usize,
+    pub to: usize,
+    /// First cell outside the lake the water flows over
+    pub saddle: (usize, usize),
+    pub volume: f64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Flooding {
+    pub lakes: Vec<Lake>,
+    pub overflows: Vec<Overflow>,
+}
+
+/// Height, cell and catchment of a spill point
+type SpillPoint = (u8, (usize, usize), usize);
+
+/// Lowest height water of lake escapes at, the cell it escapes over and catchment it flows into
+fn spill_point(arr: &Array2<u8>, rules: &BasinRules, sheds: &Catchments, lake: &[usize]) -> Option<SpillPoint> {
+    let mut in_lake = vec![false; sheds.catchments.len()];
+    lake.iter().for_each(|idx| in_lake[*idx] = true);
+    let in_lake = &in_lake;
+    lake.iter()
+        .flat_map(|idx| &sheds.catchments[*idx].cells)
+        .flat_map(|cell| rules.connectivity.neighbours(*cell, arr.dim())
+            .filter(|neighbour| !in_lake[sheds.labels[*neighbour]])
+            .map(move |neighbour| (arr[*cell].max(arr[neighbour]), neighbour, sheds.labels[neighbour])))
+        .min()
+}
+
+/// Index of lake every catchment belongs to
+fn lake_index(lakes: &[Lake], catchments: usize) -> Vec<usize> {
+    let mut lake_of = vec![0; catchments];
+    lakes.iter().enumerate()
+        .for_each(|(idx, lake)| lake.catchments.iter().for_each(|catchment| lake_of[*catchment] = idx));
+    lake_of
+}
+
+/// Level water of given volume reaches over cells of given heights, at most `cap`,
+/// and the volume that does not fit below `cap`, negative when there is room left
+fn fill(heights: &mut [f64], volume: f64, cap: Option<f64>) -> (f64, f64) {
+    heights.sort_by(|lhs, rhs| lhs.partial_cmp(rhs).unwrap());
+    let mut submerged = 0.0;
+    let mut level = f64::INFINITY;
+    for (idx, height) in heights.iter().enumerate() {
+        submerged += height;
+        // Level when water covers the lowest `idx + 1` cells
+        level = (volume + submerged) / (idx + 1) as f64;
+        if heights.get(idx + 1).is_none_or(|next| level <= *next) {
+            break;
+        }
+    }
+    match cap {
+        Some(cap) => (level.min(cap), volume - heights.iter().map(|height| (cap - height).max(0.0)).sum::<f64>()),
+        None => (level, f64::NEG_INFINITY),
+    }
+}
+
+/// Level and excess of lake filled with its volume together with its spill point
+fn fill_lake(arr: &Array2<u8>, rules: &BasinRules, sheds: &Catchments, lake: &Lake) -> (f64, f64, Option<SpillPoint>) {
+    let spill = spill_point(arr, rules, sheds, &lake.catchments);
+    let mut heights: Vec<f64> = lake.catchments.iter()
+        .flat_map(|catchment| &sheds.catchments[*catchment].cells)
+        .map(|cell| arr[*cell] as f64)
+        .collect();
+    let (level, excess) = fill(&mut heights, lake.volume, spill.map(|(height, _, _)| height as f64));
+    (level, excess, spill)
+}
+
+const TOLERANCE: f64 = 1e-9;
+
+/// Fills catchments with `rainfall` units of water per cell, lakes that fill up to a saddle spill over it.
+///
+/// Water spilling into a lake that is full up to the same saddle merges both lakes, edges of the map hold water.
+pub fn flood(arr: &Array2<u8>, rules: &BasinRules, rainfall: f64) -> Flooding {
+    let sheds = catchments(arr, rules);
+    let mut lakes: Vec<Lake> = sheds.catchments.iter().enumerate()
+        .map(|(idx, catchment)| Lake { catchments: vec![idx], volume: rainfall * catchment.area() as f64, level: 0.0, flooded: vec![] })
+        .collect();
+    let mut lake_of = lake_index(&lakes, sheds.catchments.len());
+    let mut overflows = vec![];
+    loop {
+        let mut spilled = false;
+        for idx in 0..lakes.len() {
+            let (level, excess, spill) = fill_lake(arr, rules, &sheds, &lakes[idx]);
+            lakes[idx].level = level;
+            if excess <= TOLERANCE {
+                continue;
+            }
+            let (saddle_height, saddle, to) = spill.unwrap();
+            let target = lake_of[to];
+            let (_, target_excess, target_spill) = fill_lake(arr, rules, &sheds, &lakes[target]);
+            let target_full = target_excess > -TOLERANCE;
+            overflows.push(Overflow { from: lakes[idx].catchments[0], to, saddle, volume: excess });
+            if target_full && target_spill.is_some_and(|(height, _, _)| height == saddle_height) {
+                let merged = lakes.remove(idx.max(target));
+                let kept = &mut lakes[idx.min(target)];
+                kept.catchments.extend(merged.catchments);
+                kept.catchments.sort_unstable();
+                kept.volume += merged.volume;
+                lake_of = lake_index(&lakes, sheds.catchments.len());
+            } else {
+                lakes[idx].volume -= excess;
+                lakes[target].volume += excess;
+            }
+            spilled = true;
+            break;
+        }
+        if !spilled {
+            break;
+        }
+    }
+    for lake in lakes.iter_mut() {
+        lake.flooded = lake.catchments.iter()
+            .flat_map(|catchment| &sheds.catchments[*catchment].cells)
+            .filter(|cell| (arr[**cell] as f64) < lake.level)
+            .copied()
+            .collect();
+        lake.flooded.sort_unstable();
+    }
+    Flooding { lakes, overflows }
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::arr2;
+    use crate::basins::label_basins_with;
+    use crate::flow::{catchments, drainage, flood};
+    use crate::parse;
+    use crate::rules::{BasinRules, Connectivity, Partition};
+    use crate::tests::TEST_DATA;
+
+    #[test]
+    fn drainage_and_catchments() {
+        let arr = parse(TEST_DATA.lines());
+        let rules = BasinRules::default();
+        let drains = drainage(&arr, &rules);
+        assert_eq!(drains[[0, 0]], Some((0, 1)));
+        assert_eq!(drains[[0, 1]], None);
+        assert_eq!(drains[[1, 1]], Some((0, 1)));
+        let sheds = catchments(&arr, &rules);
+        assert_eq!(sheds.catchments.len(), 4);
+        assert_eq!(sheds.catchments.iter().map(|catchment| catchment.area()).sum::<usize>(), 50);
+        assert_eq!(sheds.catchments[0].low_region, vec![(0, 1)]);
+        // Same low regions as downhill basins
+        let downhill = label_basins_with(&arr, &BasinRules::new(Connectivity::Four, None, Partition::Downhill));
+        assert!(sheds.catchments.iter().all(|catchment| downhill.basin_at(catchment.low_region[0]).unwrap().low_point == catchment.low_region[0]));
+    }
+
+    #[test]
+    fn flat_plateau_drains_to_exit() {
+        let arr = arr2(&[[5, 5, 5, 1]]);
+        let drains = drainage(&arr, &BasinRules::default());
+        assert_eq!(drains.into_raw_vec(), vec![Some((0, 1)), Some((0, 2)), Some((0, 3)), None]);
+    }
+
+    #[test]
+    fn rainfall() {
+        let arr = arr2(&[
+            [9, 9, 9, 9, 9, 9, 9],
+            [9, 1, 3, 4, 5, 2, 9],
+            [9, 9, 9, 9, 9, 9, 9],
+        ]);
+        let rules = BasinRules::default();
+        // Little rain stays in the two pools
+        let light = flood(&arr, &rules, 0.1);
+        assert_eq!(light.lakes.len(), 2);
+        assert!(light.overflows.is_empty());
+        assert!(light.lakes.iter().all(|lake| lake.level < 4.0));
+        // Left pool (3 cells in catchment out of 21) spills over the ridge at height 4 into the right one
+        let heavy = flood(&arr, &rules, 1.0);
+        assert!(!heavy.overflows.is_empty());
+        assert_eq!(heavy.overflows[0].saddle, (1, 4));
+        let total: f64 = heavy.lakes.iter().map(|lake| lake.volume).sum();
+        assert!((total - 21.0).abs() < 1e-9);
+        // Enough water merges everything into one lake above the highest ground
+        let deluge = flood(&arr, &rules, 20.0);
+        assert_eq!(deluge.lakes.len(), 1);
+        assert!((deluge.lakes[0].level - (420.0 + arr.sum() as f64) / 21.0).abs() < 1e-9);
+        assert_eq!(deluge.lakes[0].flooded.len(), 21);
+    }
+}
@@ -5,14 +5,51 @@ use std::fs;
 use std::str::Lines;
 use ndarray::{Array1, Array2};
 use fs::read_to_string;
-use std::collections::HashSet;
-use itertools::{Itertools};
+use std::fs::File;
+use std::io;
+use crate::basins::{label_basins, label_basins_with};
+use crate::flow::{catchments, flood};
+use crate::render::BasinView;
+use crate::rules::BasinRules;
+
+mod basins;
+mod rules;
+mod flow;
+mod render;
+
+/// Water poured on every cell for the flooding simulation
+const RAINFALL: f64 = 2.0;
 
 fn main() {
     let str = read_to_string("day9/input_data.dat").unwrap();
     let arr = parse(str.lines());
     println!("Part 1: {}", part_1(&arr));
     println!("Part 2: {}", part_2(&arr));
+    // Rules for catchments, flooding and the basin image can be given as second argument, see `BasinRules::from_str`
+    let rules: BasinRules = std::env::args().nth(2).map_or_else(BasinRules::default, |arg| arg.parse().expect("Unknown basin rules"));
+    let sheds = catchments(&arr, &rules);
+    println!("Catchments: {}, largest: {} cells", sheds.catchments.len(),
+             sheds.catchments.iter().map(|catchment| catchment.area()).max().unwrap_or(0));
+    let flooding = flood(&arr, &rules, RAINFALL);
+    let deepest = flooding.lakes.iter().max_by(|lhs, rhs| lhs.level.total_cmp(&rhs.level)).unwrap();
+    println!("After {} units of rain per cell: {} lakes, {} overflows, {} flooded cells, highest level {:.2}",
+             RAINFALL, flooding.lakes.len(), flooding.overflows.len(),
+             flooding.lakes.iter().map(|lake| lake.flooded.len()).sum::<usize>(), deepest.level);
+    // Basin image goes to path given as first argument, PNG unless it ends with `.ppm`, `-` prints it to terminal
+    let basins = label_basins_with(&arr, &rules);
+    let view = BasinView::new(&arr, &basins).highlight_largest(3);
+    let output = std::env::args().nth(1).unwrap_or_else(|| "day9/basins.png".to_string());
+    let written = match output.as_str() {
+        "-" => {
+            println!("{}", view.ansi_preview());
+            Ok(())
+        }
+        path if path.ends_with(".ppm") => File::create(path).and_then(|image| view.write_ppm(image)),
+        path => File::create(path).and_then(|image| view.write_png(image).map_err(io::Error::from)),
+    };
+    if let Err(err) = written {
+        println!("Basin image not written: {}", err);
+    }
 }
 
 pub fn parse(lines: Lines) -> Array2<u8> {
@@ -31,36 +68,8 @@ fn is_min(previous: Option<&u8>, x: &u8, next: Option<&u8>) -> bool {
         (Some(p), Some(n)) => p > x && x < n,
         (None, Some(n)) => x < n,
         (Some(p), None) => p > x,
-        _ => { panic!() }
-    }
-}
-
-fn explore_from_point(arr: &Array2<u8>, row_start: usize, col_start: usize) -> HashSet<(usize, usize)> {
-    arr.indexed_iter()
-        // .filter(|((row,_),_)| row_start.abs_diff(*row) <= 1)
-        .filter(|((row, col), _)| col_start.abs_diff(*col) <= 1 && row_start.abs_diff(*row) <= 1)
-        .filter(|((row, col), _)|
-            (col_start.abs_diff(*col) == 0 && row_start.abs_diff(*row) == 0) ||
-                col_start.abs_diff(*col) != row_start.abs_diff(*row)
-        )
-        .filter(|(_, &val)| val != 9)
-        .map(|(idx, _)| idx)
-        .collect()
-}
-
-fn basin_area(arr: &Array2<u8>, row: usize, col: usize) -> usize {
-    let mut points: HashSet<(usize, usize)> = HashSet::new();
-    points.insert((row, col));
-    let mut prev_len = 0;
-    while points.len() > prev_len {
-        prev_len = points.len();
-        points = points.iter()
-            .map(|(row, col)| explore_from_point(&arr, *row, *col))
-            .flatten()
-            .collect();
-        println!("points: {:?}", points);
+        (None, None) => true,
     }
-    points.len()
 }
 
 pub fn part_1(arr: &Array2<u8>) -> usize {
@@ -78,21 +87,9 @@ pub fn part_1(arr: &Array2<u8>) -> usize {
 }
 
 pub fn part_2(arr: &Array2<u8>) -> usize {
-    arr.indexed_iter()
-        .filter(|((x, y), val)| {
-            let prev = if x > &0 { arr.get([x - 1, *y]) } else { None };
-            is_min(prev, val, arr.get([x + 1, *y]))
-        }
-        )
-        .filter(|((x, y), val)| {
-            let prev = if y > &0 { arr.get([*x, y - 1]) } else { None };
-            is_min(prev, val, arr.get([*x, y + 1]))
-        }
-        ).map(|((x, y), _)| basin_area(&arr, x, y))
-        .sorted()
-        .rev()
+    label_basins(arr).sizes().iter()
         .take(3)
-        .fold(1,|acc, val|acc*val)
+        .product()
 }
 
 #[cfg(test)]
@@ -100,9 +97,10 @@ mod tests {
     extern crate test;
     use std::fs;
     use test::Bencher;
-    use crate::{basin_area, parse, part_1, part_2};
+    use crate::{parse, part_1, part_2};
+    use crate::basins::label_basins;
 
-    static TEST_DATA: &str = r#"2199943210
+    pub(crate) static TEST_DATA: &str = r#"2199943210
 3987894921
 9856789892
 8767896789
@@ -111,18 +109,19 @@ mod tests {
     #[test]
     fn with_test_data() {
         let arr = parse(TEST_DATA.lines());
-        println!("{:#?}", arr);
         assert_eq!(part_1(&arr), 15);
-        assert_eq!(part_2(&arr), 1134)
+        assert_eq!(part_2(&arr), 1134);
+        assert_eq!(part_1(&parse("5".lines())), 6);
     }
 
     #[test]
     fn basin_area_data() {
         let arr = parse(TEST_DATA.lines());
         println!("{:#?}", arr);
-        assert_eq!(basin_area(&arr,0,0),3);
-        assert_eq!(basin_area(&arr, 0, 8), 9);
-        assert_eq!(basin_area(&arr, 3, 3), 14);
+        let basins = label_basins(&arr);
+        assert_eq!(basins.basin_at((0, 0)).unwrap().size(), 3);
+        assert_eq!(basins.basin_at((0, 8)).unwrap().size(), 9);
+        assert_eq!(basins.basin_at((3, 3)).unwrap().size(), 14);
     }
 
     #[bench]
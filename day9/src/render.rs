@@ -0,0 +1,176 @@
+use std::io;
+use std::io::Write;
+use ndarray::{Array2, Zip};
+use crate::basins::BasinMap;
+
+const WALL: [u8; 3] = [64, 64, 64];
+const LOW_POINT: [u8; 3] = [255, 255, 255];
+/// Golden ratio spreads hues of consecutive labels around the colour wheel
+const HUE_STEP: f64 = 0.618_033_988_75;
+
+/// Brightness of the highest basin cell, the lowest ones are drawn at full brightness
+const MIN_VALUE: f64 = 0.25;
+
+/// Renders heightmap with basins colour-coded by label, higher cells are darker
+pub struct BasinView<'a> {
+    heights: &'a Array2<u8>,
+    basins: &'a BasinMap,
+    /// Highest cell in any basin, brightness is scaled to it
+    max_height: u8,
+    /// Labels of basins drawn at full brightness, None draws all of them
+    highlighted: Option<Vec<usize>>,
+}
+
+fn hsv_to_rgb(hue: f64, saturation: f64, value: f64) -> [u8; 3] {
+    let sector = (hue.fract() * 6.0).floor();
+    let fraction = hue.fract() * 6.0 - sector;
+    let (p, q, t) = (value * (1.0 - saturation), value * (1.0 - fraction * saturation), value * (1.0 - (1.0 - fraction) * saturation));
+    let (r, g, b) = match sector as u8 {
+        0 => (value, t, p),
+        1 => (q, value, p),
+        2 => (p, value, t),
+        3 => (p, q, value),
+        4 => (t, p, value),
+        _ => (value, p, q),
+    };
+    [r, g, b].map(|channel| (channel * 255.0).round() as u8)
+}
+
+impl<'a> BasinView<'a> {
+    pub fn new(heights: &'a Array2<u8>, basins: &'a BasinMap) -> Self {
+        let max_height = Zip::from(heights).and(&basins.labels)
+            .fold(0, |max, height, label| if label.is_some() { max.max(*height) } else { max });
+        BasinView { heights, basins, max_height, highlighted: None }
+    }
+    /// Dims all basins but the `count` largest
+    pub fn highlight_largest(mut self, count: usize) -> Self {
+        let mut labels: Vec<usize> = (0..self.basins.basins.len()).collect();
+        labels.sort_by_key(|label| std::cmp::Reverse(self.basins.basins[*label].size()));
+        labels.truncate(count);
+        self.highlighted = Some(labels);
+        self
+    }
+
+    fn is_low_point(&self, cell: (usize, usize)) -> bool {
+        self.basins.labels[cell].is_some_and(|label| self.basins.basins[label].low_point == cell)
+    }
+
+    pub fn colour(&self, cell: (usize, usize)) -> [u8; 3] {
+        let label = match self.basins.labels[cell] {
+            None => return WALL,
+            Some(_) if self.is_low_point(cell) => return LOW_POINT,
+            Some(label) => label,
+        };
+        let value = 1.0 - (1.0 - MIN_VALUE) * self.heights[cell] as f64 / self.max_height.max(1) as f64;
+        let colour = hsv_to_rgb(label as f64 * HUE_STEP, 0.7, value);
+        match &self.highlighted {
+            Some(labels) if !labels.contains(&label) => colour.map(|channel| channel / 3),
+            _ => colour,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.heights.ncols()
+    }
+    pub fn height(&self) -> usize {
+        self.heights.nrows()
+    }
+
+    /// Colour of every cell, laid out like the heightmap
+    pub fn image(&self) -> Array2<[u8; 3]> {
+        Array2::from_shape_fn(self.heights.dim(), |cell| self.colour(cell))
+    }
+
+    pub fn write_ppm<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        write!(writer, "P6\n{} {}\n255\n", self.width(), self.height())?;
+        writer.write_all(&self.image().iter().flatten().copied().collect::<Vec<u8>>())
+    }
+
+    pub fn write_png<W: Write>(&self, writer: W) -> Result<(), png::EncodingError> {
+        let mut encoder = png::Encoder::new(writer, self.width() as u32, self.height() as u32);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.write_header()?.write_image_data(&self.image().iter().flatten().copied().collect::<Vec<u8>>())
+    }
+
+    /// Heights in black on the cell colour, low points are marked with `*`
+    pub fn ansi_preview(&self) -> String {
+        self.image().rows().into_iter().enumerate()
+            .map(|(row, colours)| colours.iter().enumerate()
+                .map(|(col, [r, g, b])| {
+                    let symbol = if self.is_low_point((row, col)) { "*".to_string() } else { self.heights[(row, col)].to_string() };
+                    format!("\x1b[48;2;{};{};{};30m{}\x1b[0m", r, g, b, symbol)
+                })
+                .collect::<String>())
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::arr2;
+    use crate::basins::label_basins;
+    use crate::parse;
+    use crate::render::{BasinView, LOW_POINT, WALL};
+    use crate::tests::TEST_DATA;
+
+    #[test]
+    fn colours() {
+        let arr = parse(TEST_DATA.lines());
+        let basins = label_basins(&arr);
+        let view = BasinView::new(&arr, &basins);
+        assert_eq!(view.colour((0, 2)), WALL);
+        assert_eq!(view.colour((0, 1)), LOW_POINT);
+        assert_ne!(view.colour((0, 0)), view.colour((0, 8)));
+        let highlighted = BasinView::new(&arr, &basins).highlight_largest(3);
+        // Smallest basin is the top left one
+        assert_eq!(highlighted.colour((0, 0)), view.colour((0, 0)).map(|channel| channel / 3));
+        assert_eq!(highlighted.colour((3, 3)), view.colour((3, 3)));
+    }
+
+    #[test]
+    fn brightness_follows_highest_basin_cell() {
+        let arr = arr2(&[[0, 1, 2, 9, 0, 2]]);
+        let basins = label_basins(&arr);
+        let view = BasinView::new(&arr, &basins);
+        // Highest basin cell is drawn at a quarter of full brightness whatever the height
+        assert_eq!(view.colour((0, 2)).iter().max(), Some(&64));
+        assert_eq!(view.colour((0, 5)).iter().max(), Some(&64));
+        assert_eq!(view.colour((0, 1)).iter().max(), Some(&159));
+        assert_eq!(view.colour((0, 3)), WALL);
+    }
+
+    #[test]
+    fn export() {
+        let arr = parse(TEST_DATA.lines());
+        let basins = label_basins(&arr);
+        let view = BasinView::new(&arr, &basins).highlight_largest(3);
+        let pixel = |data: &[u8], (row, col): (usize, usize)| -> [u8; 3] {
+            let offset = (row * 10 + col) * 3;
+            data[offset..offset + 3].try_into().unwrap()
+        };
+        let mut ppm = vec![];
+        view.write_ppm(&mut ppm).unwrap();
+        let header = "P6\n10 5\n255\n".len();
+        assert!(ppm.starts_with(b"P6\n10 5\n255\n"));
+        assert_eq!(ppm.len(), header + 10 * 5 * 3);
+        let mut png = vec![];
+        view.write_png(&mut png).unwrap();
+        assert!(png.starts_with(&[0x89, b'P', b'N', b'G']));
+        let mut reader = png::Decoder::new(png.as_slice()).read_info().unwrap();
+        let mut decoded = vec![0; reader.output_buffer_size()];
+        reader.next_frame(&mut decoded).unwrap();
+        for data in [&ppm[header..], &decoded[..10 * 5 * 3]] {
+            assert_eq!(pixel(data, (0, 2)), WALL);
+            assert_eq!(pixel(data, (0, 1)), LOW_POINT);
+            assert_eq!(pixel(data, (0, 0)), view.colour((0, 0)));
+            assert_eq!(pixel(data, (3, 3)), view.colour((3, 3)));
+        }
+        let preview = view.ansi_preview();
+        assert_eq!(preview.lines().count(), 5);
+        assert_eq!(preview.matches('*').count(), 4);
+        let [r, g, b] = view.colour((0, 0));
+        assert!(preview.starts_with(&format!("\x1b[48;2;{};{};{};30m2\x1b[0m\x1b[48;2;255;255;255;30m*\x1b[0m", r, g, b)));
+    }
+}
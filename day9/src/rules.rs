@@ -0,0 +1,80 @@
+use std::str::FromStr;
+
+/// Cells considered adjacent
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Connectivity {
+    /// Up, down, left and right
+    Four,
+    /// Diagonals too
+    Eight,
+}
+
+const FOUR: [(isize, isize); 4] = [(-1, 0), (0, -1), (0, 1), (1, 0)];
+const EIGHT: [(isize, isize); 8] = [(-1, -1), (-1, 0), (-1, 1), (0, -1), (0, 1), (1, -1), (1, 0), (1, 1)];
+
+impl Connectivity {
+    /// Neighbours of cell that lie inside a grid of `(rows, cols)`
+    pub fn neighbours(&self, (row, col): (usize, usize), (rows, cols): (usize, usize)) -> impl Iterator<Item=(usize, usize)> {
+        let offsets: &'static [(isize, isize)] = match self {
+            Connectivity::Four => &FOUR,
+            Connectivity::Eight => &EIGHT,
+        };
+        offsets.iter()
+            .map(move |(dr, dc)| (row as isize + dr, col as isize + dc))
+            .filter(move |(r, c)| *r >= 0 && *c >= 0 && (*r as usize) < rows && (*c as usize) < cols)
+            .map(|(r, c)| (r as usize, c as usize))
+    }
+}
+
+/// How cells are grouped into basins
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Partition {
+    /// Connected cells below wall height form a basin
+    Walls,
+    /// Every low region collects the cells that flow down into it
+    Downhill,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct BasinRules {
+    pub connectivity: Connectivity,
+    /// Cells this high or higher belong to no basin
+    pub wall: Option<u8>,
+    pub partition: Partition,
+}
+
+impl BasinRules {
+    pub fn new(connectivity: Connectivity, wall: Option<u8>, partition: Partition) -> Self {
+        BasinRules { connectivity, wall, partition }
+    }
+    pub fn is_wall(&self, height: u8) -> bool {
+        self.wall.is_some_and(|wall| height >= wall)
+    }
+}
+
+/// Parses comma separated changes to the puzzle rules: `four`, `eight`, `walls`, `downhill`, `wall:N` or `no-wall`
+impl FromStr for BasinRules {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.split(',').try_fold(BasinRules::default(), |rules, option| match option.split_once(':') {
+            Some(("wall", height)) => height.parse().map(|height| BasinRules { wall: Some(height), ..rules }).map_err(|_| ()),
+            Some(_) => Err(()),
+            None => match option {
+                "four" => Ok(BasinRules { connectivity: Connectivity::Four, ..rules }),
+                "eight" => Ok(BasinRules { connectivity: Connectivity::Eight, ..rules }),
+                "walls" => Ok(BasinRules { partition: Partition::Walls, ..rules }),
+                "downhill" => Ok(BasinRules { partition: Partition::Downhill, ..rules }),
+                "no-wall" => Ok(BasinRules { wall: None, ..rules }),
+                _ => Err(()),
+            },
+        })
+    }
+}
+
+/// Puzzle rules, 4-connected basins separated by nines
+impl Default for BasinRules {
+    fn default() -> Self {
+        BasinRules::new(Connectivity::Four, Some(9), Partition::Walls)
+    }
+}